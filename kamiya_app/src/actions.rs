@@ -1,210 +1,309 @@
 use crate::{
-    manager::Manager,
+    args::args,
+    bundle, completions,
+    doctor::{self, Problem},
+    error::AppError,
+    git, hooks, html, man,
+    manager::{Manager, DEFAULT_PROFILE},
+    markdown, obsidian, plugin,
     term::{AskDefaultAnswers, Term},
 };
+use clap_complete::Shell;
+use globset::Glob;
 use kamiya_utils::{
-    proc::{run_editor, ProcessError},
-    tempfile::TempFile,
+    crypto::{self, CryptoError},
+    proc::{run_editor, run_pager, run_program, ProcessError},
+    tempfile::{self, TempFile},
+    walk::walk_dir,
 };
 
-use kamiya_config::Config;
-use kamiya_database::{Database, DatabaseError, Note};
-use std::{fs, mem, path::Path, process::exit};
+use kamiya_config::{self as config, Config, ConfigError};
+use kamiya_database::{Database, Note, OnConflict};
+use sha2::{Digest, Sha256};
+use std::{collections::HashSet, env, ffi::OsString, fs, iter, mem, path::Path};
 
 pub struct Actions;
 
 impl Actions {
-    pub fn take(content: &str, name: &mut String, desc: &str) {
-        let config: Config = Manager::load_config();
-        let mut database: Database = Manager::load_database();
+    pub fn take(
+        content: &str,
+        name: &mut String,
+        desc: &str,
+        secret: bool,
+    ) -> Result<(), AppError> {
+        let config: Config = Manager::load_config()?;
+        let mut database: Database = Manager::load_database()?;
 
         if name.is_empty() {
-            let new_name = match database.generate_name(&config.get_template()) {
-                Ok(name) => name,
-                Err(e) => match e {
-                    DatabaseError::NoteNotFound => {
-                        Term::fatal("Note not found!");
-                        exit(1);
-                    }
-                    DatabaseError::BadTemplate => {
-                        Term::fatal("Your configuation have bad template name. Ensure that `name_template` option have `&i`.");
-                        exit(1);
-                    }
-                },
-            };
+            let new_name = database.generate_name(&config.get_template())?;
             name.push_str(&new_name);
             mem::forget(new_name);
         }
         mem::forget(config);
 
         if database.note_exists(name) {
-            Term::fatal("Note with same name already exists!");
-            exit(1);
+            return Err(AppError::conflict("Note with same name already exists!"));
         }
 
+        let content: String = if secret {
+            crypto::encrypt(content.as_bytes(), &Self::note_passphrase(true)?)?
+        } else {
+            content.to_string()
+        };
         let new_note: Note = Note {
+            id: String::new(),
             name: name.clone(),
-            content: content.to_string(),
+            content,
             description: desc.to_string(),
+            tags: Vec::new(),
+            secret,
+            modified: 0,
         };
 
-        database.add_note(new_note);
-        Manager::write_database(database);
+        database.add_note(new_note)?;
+        let note: Note = database.get_note(name)?;
+        Manager::set_change(&format!("take: {}", note.name));
+        Manager::write_database(database)?;
         Term::success(&format!("Note have been added to database as '{}'.", name));
+        hooks::run("post_take", Some(&note))
     }
 
-    pub fn desc(name: &str, desc: &str) {
-        let mut database: Database = Manager::load_database();
+    pub fn desc(name: &str, desc: &str) -> Result<(), AppError> {
+        let mut database: Database = Manager::load_database()?;
 
-        if !database.note_exists(name) {
-            Term::fatal("Note with given name not found.");
-            exit(1);
-        }
-
-        match database.set_note_description(name, desc) {
-            Ok(_) => {}
-            Err(e) => match e {
-                DatabaseError::NoteNotFound => {
-                    Term::fatal("Note not found!");
-                    exit(1);
-                }
-                _ => panic!("Unrelated error occured."),
-            },
-        }
-        Manager::write_database(database);
+        database.set_note_description(name, desc)?;
+        Manager::set_change(&format!("desc: {}", name));
+        Manager::write_database(database)?;
         Term::success("Description changed.");
+        Ok(())
     }
 
-    pub fn add(filename: &str, name: &mut String) {
-        let mut database: Database = Manager::load_database();
+    pub fn add(filename: &str, name: &mut String, on_conflict: OnConflict) -> Result<(), AppError> {
+        let mut database: Database = Manager::load_database()?;
 
         if !Path::new(filename).exists() {
-            Term::fatal("File not found!");
-            exit(1)
+            return Err(AppError::not_found("File not found!"));
         }
 
         if name.is_empty() {
-            name.push_str(Path::new(filename).file_stem().unwrap().to_str().unwrap());
+            name.push_str(
+                &Path::new(filename)
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy(),
+            );
         }
 
-        let file_content: String = match fs::read_to_string(filename) {
-            Ok(content) => content,
-            Err(_) => {
-                Term::fatal("Failed to read file.");
-                exit(1);
-            }
-        };
+        let file_content: String =
+            fs::read_to_string(filename).map_err(|e| AppError::io("Failed to read file.", e))?;
         let new_note: Note = Note {
+            id: String::new(),
             name: name.clone(),
             content: file_content,
             description: String::new(),
+            tags: Vec::new(),
+            secret: false,
+            modified: 0,
         };
-        database.add_note(new_note);
-        Manager::write_database(database);
+        let name: String = database.insert_note(new_note, on_conflict)?;
+        Manager::set_change(&format!("add: {}", name));
+        Manager::write_database(database)?;
         Term::success(format!("Note have been added to database as '{}'.", name).as_str());
+        Ok(())
     }
 
-    pub fn rename(old_name: &str, new_name: &str) {
-        let mut database: Database = Manager::load_database();
-        if !database.note_exists(old_name) {
-            Term::fatal("Cannot find note to rename");
-            exit(1);
-        }
+    pub fn rename(old_name: &str, new_name: &str, on_conflict: OnConflict) -> Result<(), AppError> {
+        let mut database: Database = Manager::load_database()?;
 
-        match database.set_note_name(old_name, new_name) {
-            Ok(_) => {}
-            Err(e) => match e {
-                DatabaseError::NoteNotFound => {
-                    Term::fatal("Note not found!");
-                    exit(1);
-                }
-                _ => panic!("Unrelated error occured."),
-            },
-        }
-        Manager::write_database(database);
+        let new_name: String = database.rename_note(old_name, new_name, on_conflict)?;
+        Manager::set_change(&format!("rename: {} -> {}", old_name, new_name));
+        Manager::write_database(database)?;
         Term::success(&format!(
             "Note '{}' now have name '{}'.",
             old_name, new_name
         ));
+        Ok(())
     }
 
-    pub fn editor(editor: &str) {
-        let mut config: Config = Manager::load_config();
-
-        if editor.is_empty() {
-            if config.get_editor().is_empty() {
-                Term::info("Editor not set. Please set name or path to executable of the editor.");
-                Term::hint("Example: kamiya editor vim");
-                exit(1)
+    pub fn config_get(key: &str) -> Result<(), AppError> {
+        match config::get_key(&Manager::load_config_value()?, key) {
+            Some(value) if !value.is_table() => {
+                println!("{}", config::display(value));
+                Ok(())
             }
-            Term::info(&format!("Current editor: {}", config.get_editor()));
-        } else {
-            config.set_editor(editor);
-            Manager::write_config(config);
-            Term::success(&format!("Editor changed to {}", editor));
+            _ => Err(AppError::not_found(&format!("Unknown option '{}'.", key))
+                .with_hint("Use `kamiya config list` to see all options.")),
         }
     }
 
-    pub fn template(template: &str) {
-        let mut config: Config = Manager::load_config();
+    pub fn config_set(key: &str, value: &str) -> Result<(), AppError> {
+        if config::normalize_key(key) == "options.name_template" && !value.contains("&i") {
+            return Err(AppError::general("Template must contain `&i`."));
+        }
 
-        if template.is_empty() {
-            if config.get_template().is_empty() {
-                Term::info("Template not set. Set it manually.");
-                Term::hint("Example: kamiya template Note&i");
-                exit(1)
+        // Only user file is changed, so options from other layers must not leak into it.
+        let mut user_config = Manager::load_config_file(&Manager::get_config_path())?;
+        Manager::set_option(&mut user_config, key, value)?;
+        Manager::write_config(user_config)?;
+        Term::success(&format!(
+            "Option {} changed to '{}'.",
+            config::normalize_key(key),
+            value
+        ));
+        Ok(())
+    }
+
+    pub fn config_list() -> Result<(), AppError> {
+        for (key, value) in config::flatten(&Manager::load_config_value()?) {
+            println!("{} = {}", key, value);
+        }
+        Ok(())
+    }
+
+    pub fn config_edit() -> Result<(), AppError> {
+        // Broken configuration must be editable, so defaults are used in this case.
+        let is_broken = match Manager::read_config_file(&Manager::get_config_path()) {
+            Ok(value) => config::validate(&value)
+                .iter()
+                .any(|problem| !matches!(problem, ConfigError::UnknownKey(_))),
+            Err(_) => true,
+        };
+        let config: Config = match is_broken {
+            true => Config::default(),
+            false => Manager::load_config()?,
+        };
+        let editor_name: String = Self::get_editor(&config, "toml")?;
+
+        Term::work(format!("Launching {}", editor_name).as_str());
+        run_editor(&editor_name, &Manager::get_config_path(), 1)?;
+        // Catch mistakes right away, not on the next command.
+        Self::config_check()
+    }
+
+    // Check all configuration files. Unknown options are warnings, other problems are errors.
+    pub fn config_check() -> Result<(), AppError> {
+        let mut errors: usize = 0;
+        let mut warnings: usize = 0;
+        for path in Manager::get_config_files() {
+            if !Path::new(&path).exists() {
+                continue;
             }
-            Term::info(&format!(
-                "Current file extension: {}",
-                config.get_template()
-            ));
-        } else {
-            if !template.contains("&i") {
-                Term::fatal("Template must contain `&i`.");
-                exit(1);
+
+            let value = match Manager::read_config_file(&path) {
+                Ok(value) => value,
+                Err(e) => {
+                    Term::list_item(&path, &e.to_string());
+                    errors += 1;
+                    continue;
+                }
+            };
+            for problem in config::validate(&value) {
+                match problem {
+                    ConfigError::UnknownKey(_) => warnings += 1,
+                    _ => errors += 1,
+                }
+                Term::list_item(&path, &problem.to_string());
+            }
+            if let Some(toml::Value::String(template)) =
+                config::get_key(&value, "options.name_template")
+            {
+                if !template.contains("&i") {
+                    Term::list_item(&path, "Template must contain `&i`.");
+                    errors += 1;
+                }
             }
-            config.set_template(template);
-            Manager::write_config(config);
-            Term::success(&format!("Template changed to {}", template));
+            // Built-in commands have priority over aliases.
+            if let Some(toml::Value::Table(aliases)) = value.get("aliases") {
+                let commands = args();
+                for name in aliases.keys() {
+                    if commands.find_subcommand(name).is_some() {
+                        let message = format!("Alias '{}' is hidden by built-in command.", name);
+                        Term::list_item(&path, &message);
+                        warnings += 1;
+                    }
+                }
+            }
+        }
+
+        if errors > 0 {
+            return Err(AppError::parse(&format!(
+                "Found {} errors and {} warnings in configuration.",
+                errors, warnings
+            )));
+        }
+        if warnings > 0 {
+            Term::warn(&format!("Found {} warnings in configuration.", warnings));
+            return Ok(());
         }
+        Term::success("Configuration is valid.");
+        Ok(())
     }
 
-    pub fn list() {
-        let database: Database = Manager::load_database();
+    pub fn config_path(all: bool) {
+        if all {
+            Term::list_item("system", &Manager::get_system_config_path());
+            Term::list_item("user", &Manager::get_config_path());
+            Term::list_item(
+                "profile",
+                &Manager::get_profile_config_path(&Manager::get_profile()),
+            );
+            return;
+        }
+        println!("{}", Manager::get_config_path());
+    }
+
+    pub fn list(no_pager: bool) -> Result<(), AppError> {
+        let database: Database = Manager::load_database()?;
         let notes: Vec<Note> = database.get_notes();
         if notes.is_empty() {
-            Term::fatal("Noting added to storage!");
-            exit(1);
+            return Err(AppError::not_found("Noting added to storage!"));
         }
 
-        Term::title("Notes in storage:");
+        let mut output: Vec<String> = vec![Term::format_title("Notes in storage:")];
         for i in &notes {
-            if i.description.is_empty() {
-                Term::list_item(&i.name, "");
-            } else {
-                Term::list_item(&i.name, &i.description.clone());
-            }
+            output.push(Term::format_list_item(&i.name, &Self::describe(i)));
         }
+        Self::show(&output.join("\n"), no_pager)
     }
 
-    pub fn search(pattern: &str) {
-        let database: Database = Manager::load_database();
+    // Description of note for lists, with its tags and mark for secret notes.
+    fn describe(note: &Note) -> String {
+        let mut desc: String = note.description.clone();
+        if note.secret {
+            desc.push_str(" (secret)");
+        }
+        for tag in &note.tags {
+            desc.push_str(&format!(" #{}", tag));
+        }
+        desc.trim_start().to_string()
+    }
+
+    pub fn search(pattern: &str, no_pager: bool) -> Result<(), AppError> {
+        let database: Database = Manager::load_database()?;
         let mut found_notes: Vec<String> = vec![];
 
         for i in database.get_notes().iter() {
             if i.name.contains(pattern) {
-                found_notes.push(format!("\x1b[4m{}\x1b[0m\x1b[1m", pattern));
+                // Found part of the name is underlined.
+                let name: String = match pattern.is_empty() {
+                    true => i.name.clone(),
+                    false => i
+                        .name
+                        .replace(pattern, &format!("\x1b[4m{}\x1b[24m", pattern)),
+                };
+                found_notes.push(Term::format_list_item(&name, &Self::describe(i)));
             }
         }
 
-        Term::title(format!("Found {} notes.", found_notes.len()).as_str());
-        for a in found_notes {
-            Term::message(&a);
-        }
+        let mut output: Vec<String> = vec![Term::format_title(
+            format!("Found {} notes.", found_notes.len()).as_str(),
+        )];
+        output.extend(found_notes);
+        Self::show(&output.join("\n"), no_pager)
     }
 
-    pub fn save(name: &str, filename: &mut String) {
-        let database: Database = Manager::load_database();
+    pub fn save(name: &str, filename: &mut String) -> Result<(), AppError> {
+        let database: Database = Manager::load_database()?;
 
         if filename.is_empty() {
             filename.push_str(name);
@@ -212,247 +311,901 @@ impl Actions {
         }
 
         Term::work("Writing note content to file...");
-        let note = match database.get_note(name) {
-            Ok(note) => note,
-            Err(e) => match e {
-                DatabaseError::NoteNotFound => {
-                    Term::fatal("Note not found!");
-                    exit(1);
-                }
-                _ => panic!("Unrelated error occured."),
-            },
-        };
-        match fs::write(&filename, note.content) {
-            Ok(_s) => {
-                Term::success(
-                    format!("Note content saved as file called '{}'.", filename).as_str(),
-                );
-            }
-            Err(_err) => {
-                Term::fatal("Failed to write to file. Maybe permissions issue?");
-                exit(1);
-            }
-        }
+        let note = database.get_note(name)?;
+        fs::write(&filename, Self::read_content(&note)?)
+            .map_err(|e| AppError::io("Failed to write to file. Maybe permissions issue?", e))?;
+        Term::success(format!("Note content saved as file called '{}'.", filename).as_str());
+        Ok(())
     }
 
-    pub fn open(name: &str) {
-        let config: Config = Manager::load_config();
-        let mut database: Database = Manager::load_database();
+    pub fn open(name: &str, line: usize) -> Result<(), AppError> {
+        let config: Config = Manager::load_config()?;
+        let mut database: Database = Manager::load_database()?;
 
-        let note = match database.get_note(name) {
-            Ok(note) => note,
-            Err(e) => match e {
-                DatabaseError::NoteNotFound => {
-                    Term::fatal("Note not found!");
-                    exit(1);
-                }
-                _ => panic!("Unrelated error occured."),
-            },
-        };
-        let tmpfile = match TempFile::new(name) {
-            Ok(provider) => provider,
-            Err(_) => {
-                Term::fatal("Failed initialize temporary file due to unknown error.");
-                exit(1);
+        let note = database.get_note(name)?;
+        // Extension helps editor to pick the right syntax highlighting.
+        let extension: String = match Path::new(name).extension() {
+            Some(extension)
+                if extension
+                    .to_string_lossy()
+                    .chars()
+                    .all(char::is_alphanumeric) =>
+            {
+                extension.to_string_lossy().to_string()
             }
+            _ => String::from("md"),
         };
+        let editor_name: String = Self::get_editor(&config, &extension)?;
 
-        let tmpfile_path: String = tmpfile.get_path();
-        fs::write(tmpfile_path.clone(), note.content.clone())
-            .expect("Failed to write content of note to temporary file.");
-        let editor_name: String = config.get_editor().to_string();
+        let passphrase: Option<String> = match note.secret {
+            true => Some(Self::note_passphrase(false)?),
+            false => None,
+        };
+        let content: String = match &passphrase {
+            Some(passphrase) => Self::decrypt_content(&note, passphrase)?,
+            None => note.content.clone(),
+        };
+
+        let new_content: String =
+            Self::edit(&editor_name, &extension, &content, line, note.secret)?;
+
+        if Sha256::digest(&new_content) == Sha256::digest(&content) {
+            Term::info("No changes were made.");
+            return Ok(());
+        }
+
+        Term::work("Saving changes...");
+        let new_content: String = match &passphrase {
+            Some(passphrase) => crypto::encrypt(new_content.as_bytes(), passphrase)?,
+            None => new_content,
+        };
+        database.set_note_content(name, &new_content)?;
+        let note: Note = database.get_note(name)?;
+        Manager::set_change(&format!("edit: {}", note.name));
+        Manager::write_database(database)?;
+        Term::success("Changes have been saved.");
+        hooks::run("post_edit", Some(&note))
+    }
+
+    // Get editor for files with given extension. Falls back to `$VISUAL` and `$EDITOR`.
+    fn get_editor(config: &Config, extension: &str) -> Result<String, AppError> {
+        let mut editor_name: String = config.get_editor_for(extension);
+        for variable in ["VISUAL", "EDITOR"] {
+            if editor_name.is_empty() {
+                editor_name = env::var(variable).unwrap_or_default();
+            }
+        }
         if editor_name.is_empty() {
-            Term::fatal("Edtior not set properly. Please run Kamiya with `editor` command and see if it's set or not.");
-            Term::hint("If not or set not correctly, use `editor` command to specify it. Example: `kamiya editor vim`");
-            exit(1);
+            return Err(AppError::editor(
+                "Editor is not set and neither `$VISUAL` nor `$EDITOR` is set.",
+            )
+            .with_hint("Set it with `kamiya config set editor vim` or set `$EDITOR`."));
         }
+        Ok(editor_name)
+    }
+
+    // Edit content in temporary file. File is removed when this function returns,
+    // unless editor has failed and user decided to keep it.
+    fn edit(
+        editor_name: &str,
+        extension: &str,
+        content: &str,
+        line: usize,
+        secret: bool,
+    ) -> Result<String, AppError> {
+        let tmpfile = TempFile::new(Some(extension))
+            .map_err(|e| AppError::io("Failed initialize temporary file.", e))?;
+
+        let tmpfile_path: String = tmpfile.get_path();
+        fs::write(&tmpfile_path, content)
+            .map_err(|e| AppError::io("Failed to write content of note to temporary file.", e))?;
 
         Term::work(format!("Launching {}", editor_name).as_str());
 
-        match run_editor(&editor_name, &tmpfile_path) {
+        match run_editor(editor_name, &tmpfile_path, line) {
             Ok(_) => {}
-            Err(e) => match e {
-                ProcessError::BadExitCode => {
-                    Term::fatal("Editor has exited with bad exit code.");
-                    tmpfile
-                        .destroy()
-                        .expect("Failed to destroy temporary file.");
-                    exit(1);
-                }
-                ProcessError::Interrupted => {
-                    Term::fatal("Editor process has been interrupted. Exiting...");
-                    tmpfile
-                        .destroy()
-                        .expect("Failed to destroy temporary file.");
-                    exit(1);
-                }
-                ProcessError::ExecutableNotFound => {
-                    Term::fatal("Editor executable not found. Exiting...");
-                    tmpfile
-                        .destroy()
-                        .expect("Failed to destroy temporary file.");
-                    exit(1);
-                }
-                ProcessError::Unknown => {
-                    Term::fatal("Unknown error occured. Exiting...");
-                    tmpfile
-                        .destroy()
-                        .expect("Failed to destroy temporary file.");
-                    exit(1);
+            Err(ProcessError::BadExitCode(_)) => {
+                Term::warn("Editor has exited with bad exit code.");
+                let answer =
+                    Term::ask_yn("Do you want to save changes anyway?", AskDefaultAnswers::No)?;
+                if let AskDefaultAnswers::No = answer {
+                    let error = AppError::editor("Changes have been discarded.");
+                    // Content of secret notes must not stay on disk as plain text.
+                    if secret {
+                        return Err(error);
+                    }
+                    return Err(
+                        error.with_hint(&format!("Your changes are kept in '{}'.", tmpfile.keep()))
+                    );
                 }
-            },
+            }
+            Err(e) => return Err(e.into()),
         }
 
-        Term::work("Saving changes...");
-        let new_content: String = fs::read_to_string(tmpfile_path).expect("Error");
-        tmpfile.destroy().unwrap();
-        database.set_note_content(name, &new_content).unwrap();
-        Manager::write_database(database);
-        Term::success("Changes have been saved.");
+        fs::read_to_string(&tmpfile_path)
+            .map_err(|e| AppError::io("Failed to read temporary file.", e))
     }
 
-    pub fn get(name: &str) {
-        let database: Database = Manager::load_database();
+    pub fn get(name: &str, no_pager: bool) -> Result<(), AppError> {
+        let database: Database = Manager::load_database()?;
 
-        let note = match database.get_note(name) {
-            Ok(note) => note,
-            Err(e) => match e {
-                DatabaseError::NoteNotFound => {
-                    Term::fatal("Note not found!");
-                    exit(1);
-                }
-                _ => panic!("Unrelated error occured."),
-            },
+        let note = database.get_note(name)?;
+        Self::show(Self::read_content(&note)?.trim_end(), no_pager)
+    }
+
+    pub fn delete(name: &str) -> Result<(), AppError> {
+        let mut database: Database = Manager::load_database()?;
+
+        let note: Note = database.get_note(name)?;
+        database.remove_note(name)?;
+        Manager::set_change(&format!("delete: {}", note.name));
+        Manager::write_database(database)?;
+        Term::success("Note deleted!.");
+        hooks::run("post_delete", Some(&note))
+    }
+
+    pub fn export(path: &str, format: &str) -> Result<(), AppError> {
+        let database: Database = Manager::load_database()?;
+
+        // Markdown notes and site can be exported into existing empty directory.
+        let is_empty_dir = match fs::read_dir(path) {
+            Ok(mut entries) => format != "json" && entries.next().is_none(),
+            Err(_) => false,
         };
+        if Path::new(path).exists() && !is_empty_dir {
+            return Err(AppError::conflict(&format!(
+                "'{}' already exists. Specify new path or remove if its not needed.",
+                path
+            )));
+        }
 
-        println!("{}", note.content.trim_end());
+        match format {
+            "markdown" => Self::export_markdown(path, &database),
+            "html" => Self::export_html(path, &database),
+            _ => Self::export_json(path, &database),
+        }
     }
 
-    pub fn delete(name: &str) {
-        let mut database: Database = Manager::load_database();
+    fn export_json(path: &str, database: &Database) -> Result<(), AppError> {
+        Term::work("Exporting database...");
+        let backup_config = serde_json::to_string(database)
+            .map_err(|e| AppError::parse(&format!("Failed to serialize database. {}", e)))?;
+        fs::write(path, backup_config)
+            .map_err(|e| AppError::io("Failed to write content to file.", e))?;
+        Term::success("File saved!");
+        Term::hint(&format!("Database exported as '{}'.", path));
+        Ok(())
+    }
+
+    fn export_markdown(path: &str, database: &Database) -> Result<(), AppError> {
+        Term::work("Exporting notes as markdown files...");
+        fs::create_dir_all(path).map_err(|e| AppError::io("Failed to create directory.", e))?;
 
-        if !database.note_exists(name) {
-            Term::fatal("Note not found!");
-            exit(1);
+        let mut taken: HashSet<String> = HashSet::new();
+        let mut skipped: usize = 0;
+        for note in database.get_notes() {
+            // Content of secret notes is encrypted, so it can't be saved as markdown.
+            if note.secret {
+                skipped += 1;
+                continue;
+            }
+            let filename = markdown::unique_slug(&note.name, &mut taken) + ".md";
+            fs::write(
+                Path::new(path).join(&filename),
+                markdown::render_note(&note),
+            )
+            .map_err(|e| {
+                AppError::io(&format!("Failed to write note '{}' to file.", note.name), e)
+            })?;
         }
 
-        match database.remove_note(name) {
-            Ok(_) => {}
-            Err(e) => match e {
-                DatabaseError::NoteNotFound => {
-                    Term::fatal("Note not found!");
-                    exit(1);
-                }
-                _ => panic!("Unrelated error occured."),
-            },
+        Term::success(&format!("Exported {} notes.", taken.len()));
+        if skipped > 0 {
+            Term::warn(&format!("Skipped {} secret notes.", skipped));
         }
-        Manager::write_database(database);
-        Term::success("Note deleted!.");
+        Term::hint(&format!("Notes saved in '{}'.", path));
+        Ok(())
+    }
+
+    fn export_html(path: &str, database: &Database) -> Result<(), AppError> {
+        Term::work("Rendering notes as HTML...");
+        let notes: Vec<Note> = database.get_notes();
+        html::write_site(Path::new(path), &notes)
+            .map_err(|e| AppError::io("Failed to write site.", e))?;
+
+        Term::success(&format!("Exported {} notes.", notes.len()));
+        Term::hint(&format!(
+            "Open '{}' to view notes.",
+            Path::new(path).join("index.html").display()
+        ));
+        Ok(())
     }
 
-    pub fn export(path: &str) {
-        let database: Database = Manager::load_database();
+    pub fn export_bundle(path: &str) -> Result<(), AppError> {
+        let database: Database = Manager::load_database()?;
 
         if Path::new(path).exists() {
-            Term::fatal(&format!(
+            return Err(AppError::conflict(&format!(
                 "'{}' already exists. Specify new path or remove if its not needed.",
                 path
+            )));
+        }
+
+        Term::work("Packing notes into bundle...");
+        let notes: Vec<Note> = database.get_notes();
+        bundle::write_bundle(Path::new(path), &notes)?;
+        Term::success(&format!("Exported {} notes.", notes.len()));
+        Term::hint(&format!("Bundle saved as '{}'.", path));
+        Ok(())
+    }
+
+    pub fn import(
+        filename: &str,
+        on_conflict: Option<OnConflict>,
+        interactive: bool,
+    ) -> Result<(), AppError> {
+        let mut database: Database = Manager::load_database()?;
+
+        if !Path::new(filename).exists() {
+            return Err(AppError::not_found("Cant find new database."));
+        }
+
+        if !Path::new(filename)
+            .extension()
+            .map(|ext| ext.eq("json"))
+            .unwrap_or(false)
+        {
+            return Err(AppError::parse("Database file must be in JSON format."));
+        }
+
+        Term::work("Getting new database content...");
+        let new_db_file: String = fs::read_to_string(filename).map_err(|e| {
+            AppError::io("Failed to read new database file. Maybe it's corrupted.", e)
+        })?;
+        let new_db: Database = serde_json::from_str(new_db_file.as_str()).map_err(|e| {
+            AppError::parse(&format!(
+                "Failed to serialize new database because it's bad formatted. Error: {}",
+                e
+            ))
+        })?;
+        Term::work("Importing...");
+        Self::merge_notes(&mut database, new_db.get_notes(), on_conflict, interactive)?;
+        Manager::write_database(database)?;
+        Term::success("Import finished.");
+        Ok(())
+    }
+
+    pub fn import_bundle(
+        path: &str,
+        on_conflict: Option<OnConflict>,
+        interactive: bool,
+    ) -> Result<(), AppError> {
+        let mut database: Database = Manager::load_database()?;
+
+        if !Path::new(path).is_file() {
+            return Err(AppError::not_found("Cant find bundle."));
+        }
+
+        Term::work("Unpacking and verifying bundle...");
+        let notes = bundle::read_bundle(Path::new(path))?;
+
+        Term::work("Importing...");
+        Self::merge_notes(&mut database, notes, on_conflict, interactive)?;
+        Manager::write_database(database)?;
+        Term::success("Import finished.");
+        Ok(())
+    }
+
+    pub fn import_dir(
+        path: &str,
+        glob: &str,
+        on_conflict: Option<OnConflict>,
+        interactive: bool,
+    ) -> Result<(), AppError> {
+        let mut database: Database = Manager::load_database()?;
+
+        if !Path::new(path).is_dir() {
+            return Err(AppError::not_found("Cant find directory to import."));
+        }
+
+        let matcher = match Glob::new(glob) {
+            Ok(glob) => glob.compile_matcher(),
+            Err(e) => return Err(AppError::parse(&format!("Bad glob pattern. Error: {}", e))),
+        };
+
+        Term::work("Collecting files...");
+        let files =
+            walk_dir(Path::new(path)).map_err(|e| AppError::io("Failed to read directory.", e))?;
+
+        let mut notes: Vec<Note> = Vec::new();
+        for file in files {
+            let relative = file.strip_prefix(path).unwrap_or(&file);
+            if !matcher.is_match(relative) {
+                continue;
+            }
+
+            let content = match fs::read_to_string(&file) {
+                Ok(content) => content,
+                Err(_) => {
+                    Term::warn(&format!("Skipping unreadable file '{}'.", file.display()));
+                    continue;
+                }
+            };
+            let name = file.file_stem().unwrap_or_default().to_string_lossy();
+            notes.push(markdown::parse_note(&content, &name));
+        }
+
+        Term::work("Importing...");
+        Self::merge_notes(&mut database, notes, on_conflict, interactive)?;
+        Manager::write_database(database)?;
+        Term::success("Import finished.");
+        Ok(())
+    }
+
+    pub fn import_obsidian(
+        path: &str,
+        on_conflict: Option<OnConflict>,
+        interactive: bool,
+    ) -> Result<(), AppError> {
+        let mut database: Database = Manager::load_database()?;
+
+        if !Path::new(path).is_dir() {
+            return Err(AppError::not_found("Cant find Obsidian vault."));
+        }
+
+        Term::work("Reading Obsidian vault...");
+        let vault = obsidian::read_vault(Path::new(path))
+            .map_err(|e| AppError::io("Failed to read vault.", e))?;
+
+        Term::work("Importing...");
+        let notes_count = vault.notes.len();
+        Self::merge_notes(&mut database, vault.notes, on_conflict, interactive)?;
+        Manager::write_database(database)?;
+        Term::success(&format!(
+            "Import finished. Processed {} notes.",
+            notes_count
+        ));
+
+        if !vault.attachments.is_empty() {
+            Term::warn(&format!(
+                "{} attachments were not imported:",
+                vault.attachments.len()
             ));
-            exit(1);
+            for attachment in &vault.attachments {
+                Term::message(&attachment.display().to_string());
+            }
+        }
+        if !vault.unreadable.is_empty() {
+            Term::warn(&format!(
+                "{} notes could not be read:",
+                vault.unreadable.len()
+            ));
+            for file in &vault.unreadable {
+                Term::message(&file.display().to_string());
+            }
         }
+        Ok(())
+    }
 
-        Term::work("Exporting database...");
-        let backup_config = serde_json::to_string(&database).unwrap();
-        match fs::write(path, backup_config) {
-            Ok(_) => {
-                Term::success("File saved!");
-                Term::hint(&format!("Database exported as '{}'.", path));
+    pub fn encrypt(yes: bool) -> Result<(), AppError> {
+        if Manager::is_database_encrypted() {
+            return Err(AppError::conflict("Database is already encrypted."));
+        }
+        let database: Database = Manager::load_database()?;
+
+        // Encryption doesn't change history, so earlier commits still have notes as plain text.
+        if git::is_repo() && !yes {
+            Term::warn(
+                "Git history of data directory keeps earlier versions of notes as plain text.",
+            );
+            let answer = Term::ask_yn("Encrypt database anyway?", AskDefaultAnswers::No)?;
+            if let AskDefaultAnswers::No = answer {
+                return Err(AppError::general("Cancelled."));
             }
-            Err(i) => {
-                Term::fatal(&format!("Failed to write content to file. Error: {}", i));
+        }
+
+        let passphrase: String = match env::var("KAMIYA_PASSPHRASE") {
+            Ok(passphrase) => passphrase,
+            Err(_) => {
+                let passphrase = Term::ask_passphrase("New passphrase")?;
+                if Term::ask_passphrase("Repeat passphrase")? != passphrase {
+                    return Err(AppError::general("Passphrases don't match."));
+                }
+                passphrase
             }
+        };
+        if passphrase.is_empty() {
+            return Err(AppError::general("Passphrase can't be empty."));
         }
+
+        Term::work("Encrypting database...");
+        Manager::set_passphrase(Some(passphrase));
+        Manager::write_database(database)?;
+        Term::success("Database encrypted.");
+        Term::hint("Remember your passphrase. Notes can't be recovered without it.");
+        Ok(())
     }
 
-    pub fn import(filename: &str, replace: bool, interactive: bool) {
-        let mut database: Database = Manager::load_database();
+    pub fn decrypt() -> Result<(), AppError> {
+        if !Manager::is_database_encrypted() {
+            return Err(AppError::conflict("Database is not encrypted."));
+        }
+        let database: Database = Manager::load_database()?;
 
-        if !Path::new(filename).exists() {
-            Term::fatal("Cant find new database.");
-            exit(1);
+        Term::work("Decrypting database...");
+        Manager::set_passphrase(None);
+        Manager::write_database(database)?;
+        Term::success("Database decrypted.");
+        Ok(())
+    }
+
+    pub fn lock(name: &str) -> Result<(), AppError> {
+        let mut database: Database = Manager::load_database()?;
+
+        let note = database.get_note(name)?;
+        if note.secret {
+            return Err(AppError::conflict("Note is already secret."));
         }
 
-        if !Path::new(filename).extension().unwrap().eq("json") {
-            Term::fatal("Database file must be in JSON format.");
-            exit(1);
+        let content = crypto::encrypt(note.content.as_bytes(), &Self::note_passphrase(true)?)?;
+        database.set_note_content(name, &content)?;
+        database.set_note_secret(name, true)?;
+        Manager::set_change(&format!("lock: {}", note.name));
+        Manager::write_database(database)?;
+        Term::success("Note is secret now.");
+        Ok(())
+    }
+
+    pub fn unlock(name: &str) -> Result<(), AppError> {
+        let mut database: Database = Manager::load_database()?;
+
+        let note = database.get_note(name)?;
+        if !note.secret {
+            return Err(AppError::conflict("Note is not secret."));
         }
 
-        Term::work("Getting new database content...");
-        let new_db_file: String = match fs::read_to_string(filename) {
-            Ok(path) => path,
+        let content = Self::read_content(&note)?;
+        database.set_note_content(name, &content)?;
+        database.set_note_secret(name, false)?;
+        Manager::set_change(&format!("unlock: {}", note.name));
+        Manager::write_database(database)?;
+        Term::success("Note is not secret anymore.");
+        Ok(())
+    }
+
+    pub fn git_init() -> Result<(), AppError> {
+        if git::is_repo() {
+            return Err(AppError::conflict(
+                "Data directory is already a git repository.",
+            ));
+        }
+
+        git::init().map_err(git::error)?;
+        Term::success(&format!(
+            "History of notes is kept in '{}' now.",
+            Manager::get_data_dir()
+        ));
+        Term::hint("Use `kamiya log` to see it.");
+        Ok(())
+    }
+
+    pub fn log(limit: Option<usize>, patch: bool, no_pager: bool) -> Result<(), AppError> {
+        if !git::is_repo() {
+            return Err(AppError::not_found("History of notes is not kept.")
+                .with_hint("Use `kamiya git init` to start keeping it."));
+        }
+
+        let log: String = git::log(limit, patch, Term::is_interactive()).map_err(git::error)?;
+        if log.trim().is_empty() {
+            Term::info("History is empty.");
+            return Ok(());
+        }
+        Self::show(log.trim_end(), no_pager)
+    }
+
+    // Get passphrase for secret notes from `KAMIYA_NOTE_PASSPHRASE` or ask user for it.
+    fn note_passphrase(confirm: bool) -> Result<String, AppError> {
+        let passphrase = match env::var("KAMIYA_NOTE_PASSPHRASE") {
+            Ok(passphrase) => passphrase,
             Err(_) => {
-                Term::fatal("Failed to read new database file. Maybe it's corrupted.");
-                exit(1);
+                let passphrase = Term::ask_passphrase("Passphrase for the note")?;
+                if confirm && Term::ask_passphrase("Repeat passphrase")? != passphrase {
+                    return Err(AppError::general("Passphrases don't match."));
+                }
+                passphrase
             }
         };
-        let new_db: Database = match serde_json::from_str(new_db_file.as_str()) {
-            Ok(path) => path,
-            Err(_) => {
-                Term::fatal("Failed to serialize new database because it's bad formatted.");
-                exit(1);
+        if passphrase.is_empty() {
+            return Err(AppError::general("Passphrase can't be empty."));
+        }
+        Ok(passphrase)
+    }
+
+    fn decrypt_content(note: &Note, passphrase: &str) -> Result<String, AppError> {
+        match crypto::decrypt(&note.content, passphrase) {
+            Ok(content) => Ok(String::from_utf8_lossy(&content).to_string()),
+            Err(CryptoError::BadPassphrase) => {
+                Err(AppError::general("Wrong passphrase for the note."))
+            }
+            Err(CryptoError::BadFormat) => {
+                Err(AppError::parse("Content of secret note is corrupted."))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Get content of the note, asking for passphrase if note is secret.
+    fn read_content(note: &Note) -> Result<String, AppError> {
+        match note.secret {
+            true => Self::decrypt_content(note, &Self::note_passphrase(false)?),
+            false => Ok(note.content.clone()),
+        }
+    }
+
+    pub fn profile_create(name: &str) -> Result<(), AppError> {
+        Manager::validate_profile_name(name)?;
+        if Manager::profile_exists(name) {
+            return Err(AppError::conflict("Profile with same name already exists!"));
+        }
+
+        fs::create_dir_all(Manager::get_profile_dir(name))
+            .map_err(|e| AppError::io("Failed to create profile directory.", e))?;
+        Manager::write_private(
+            Path::new(&Manager::get_profile_database_path(name)),
+            Manager::serialize_database(&Database::default())?.as_bytes(),
+        )
+        .map_err(|e| AppError::io("Failed to create database for profile.", e))?;
+        Term::success(&format!("Profile '{}' created.", name));
+        Term::hint(&format!(
+            "Options in '{}' will override your configuration for this profile.",
+            Manager::get_profile_config_path(name)
+        ));
+        Ok(())
+    }
+
+    pub fn profile_list() {
+        let active: String = Manager::get_profile();
+        Term::title("Profiles:");
+        for profile in Manager::get_profiles() {
+            if profile == active {
+                Term::list_item(&profile, "(active)");
+            } else {
+                Term::list_item(&profile, "");
+            }
+        }
+    }
+
+    pub fn profile_use(name: &str) -> Result<(), AppError> {
+        Manager::validate_profile_name(name)?;
+        if !Manager::profile_exists(name) {
+            return Err(AppError::not_found("Profile not found!"));
+        }
+
+        fs::write(Manager::get_active_profile_path(), name)
+            .map_err(|e| AppError::io("Failed to save active profile.", e))?;
+        Term::success(&format!("Now using profile '{}'.", name));
+        Ok(())
+    }
+
+    pub fn profile_delete(name: &str, yes: bool) -> Result<(), AppError> {
+        Manager::validate_profile_name(name)?;
+        if name == DEFAULT_PROFILE {
+            return Err(AppError::general("Default profile cannot be deleted."));
+        }
+        if !Manager::profile_exists(name) {
+            return Err(AppError::not_found("Profile not found!"));
+        }
+        if Manager::get_profile() == name {
+            return Err(AppError::conflict(
+                "Cannot delete active profile. Switch to another one first.",
+            ));
+        }
+
+        if !yes {
+            let answer = Term::ask_yn(
+                &format!("All notes in profile '{}' will be lost. Continue?", name),
+                AskDefaultAnswers::No,
+            )?;
+            if let AskDefaultAnswers::No = answer {
+                return Err(AppError::general("Cancelled."));
+            }
+        }
+
+        fs::remove_dir_all(Manager::get_profile_dir(name))
+            .map_err(|e| AppError::io("Failed to delete profile.", e))?;
+        Term::success(&format!("Profile '{}' deleted.", name));
+        Ok(())
+    }
+
+    // Check configuration, database and files of Kamiya, and repair what can be repaired safely.
+    pub fn doctor(fix: bool) -> Result<(), AppError> {
+        let mut problems: Vec<Problem> = Vec::new();
+        let database_path: String = Manager::get_database_path();
+        let config_files: Vec<String> = Manager::get_config_files()
+            .into_iter()
+            .filter(|path| Path::new(path).exists())
+            .collect();
+
+        let config: Config = Manager::load_config_or_default();
+
+        Term::work("Checking configuration...");
+        for path in &config_files {
+            if let Ok(content) = fs::read_to_string(path) {
+                problems.extend(doctor::check_config(path, &content));
+            }
+        }
+
+        Term::work("Checking database...");
+        let mut notes: Option<Vec<Note>> = match Manager::load_database() {
+            Ok(database) => Some(database.get_notes()),
+            Err(e) => {
+                problems.push(Problem::new(&database_path, &e.to_string(), false));
+                None
             }
         };
-        Term::work("Importing...");
-        for i in new_db.get_notes() {
-            if database.note_exists(&i.name) {
-                if replace {
-                    Term::work(&format!(
-                        "Replacing data of `{}` with from new one.",
-                        &i.name
-                    ));
-                    database.set_note_content(&i.name, &i.content).unwrap();
-                    database
-                        .set_note_description(&i.name, &i.description)
-                        .unwrap();
-                }
+        let name_problems: Vec<Problem> = match &notes {
+            Some(notes) => doctor::check_names(notes, config.get_ignore_case()),
+            None => Vec::new(),
+        };
+        let has_name_problems: bool = !name_problems.is_empty();
+        problems.extend(name_problems);
+
+        Term::work("Checking permissions...");
+        let mut files: Vec<(String, bool)> = config_files
+            .iter()
+            .map(|path| (path.clone(), false))
+            .collect();
+        files.push((database_path.clone(), true));
+        for (path, private) in &files {
+            problems.extend(doctor::check_permissions(path, *private));
+        }
 
-                if interactive {
+        Term::work("Looking for temporary files...");
+        let leftovers = tempfile::find_leftovers().unwrap_or_default();
+        for file in &leftovers {
+            problems.push(Problem::new(
+                &file.display().to_string(),
+                "Temporary file left by Kamiya. It may contain content of notes.",
+                true,
+            ));
+        }
+
+        if problems.is_empty() {
+            Term::success("No problems found.");
+            return Ok(());
+        }
+        Term::title(&format!("Found {} problems:", problems.len()));
+        for problem in &problems {
+            let mut message: String = problem.message.clone();
+            if problem.fixable {
+                message.push_str(" (fixable)");
+            }
+            Term::list_item(&problem.subject, &message);
+        }
+
+        let fixable: usize = problems.iter().filter(|problem| problem.fixable).count();
+        if !fix {
+            let error = AppError::general(&format!("Found {} problems.", problems.len()));
+            if fixable == 0 {
+                return Err(error);
+            }
+            return Err(error.with_hint(&format!(
+                "Use `kamiya doctor --fix` to fix {} of them.",
+                fixable
+            )));
+        }
+        if fixable == 0 {
+            return Err(AppError::general(
+                "None of the problems can be fixed automatically.",
+            ));
+        }
+
+        let backup: String = Manager::backup()?;
+        Term::info(&format!("Backup saved in '{}'.", backup));
+
+        for (path, private) in &files {
+            if doctor::check_permissions(path, *private)
+                .iter()
+                .any(|problem| problem.fixable)
+            {
+                doctor::fix_permissions(path, *private).map_err(|e| {
+                    AppError::io(&format!("Failed to fix permissions of '{}'.", path), e)
+                })?;
+                Term::work(&format!("Fixed permissions of '{}'.", path));
+            }
+        }
+
+        if let (Some(notes), true) = (&mut notes, has_name_problems) {
+            let renamed: usize =
+                doctor::fix_names(notes, &config.get_template(), config.get_ignore_case());
+            let mut database: Database = Database::default();
+            for note in notes.drain(..) {
+                database.add_note(note)?;
+            }
+            Manager::write_database(database)?;
+            Term::work(&format!("Renamed {} notes.", renamed));
+        }
+
+        let temp_dir = Path::new(&backup).join("temp");
+        for file in &leftovers {
+            doctor::move_file(file, &temp_dir)
+                .map_err(|e| AppError::io(&format!("Failed to move '{}'.", file.display()), e))?;
+        }
+        if !leftovers.is_empty() {
+            Term::work(&format!(
+                "Moved {} temporary files to '{}'.",
+                leftovers.len(),
+                temp_dir.display()
+            ));
+        }
+
+        if fixable < problems.len() {
+            return Err(AppError::general(&format!(
+                "Fixed {} problems, {} must be fixed manually.",
+                fixable,
+                problems.len() - fixable
+            )));
+        }
+        Term::success(&format!("Fixed {} problems.", fixable));
+        Ok(())
+    }
+
+    // Add new notes to the database, resolving notes with the same name.
+    // Notes with taken names are skipped if there is no strategy and user isn't asked.
+    fn merge_notes(
+        database: &mut Database,
+        notes: Vec<Note>,
+        on_conflict: Option<OnConflict>,
+        interactive: bool,
+    ) -> Result<(), AppError> {
+        for i in notes {
+            if !database.note_exists(&i.name) {
+                Term::work(format!("Adding new note: {}", &i.name.clone()).as_str());
+                database.add_note(i)?;
+                continue;
+            }
+
+            let on_conflict: Option<OnConflict> = match interactive {
+                true => {
                     let answer = Term::ask_yn(
                         &format!(
                             "Note with name `{}` found in current storage. Do you want to replace?",
                             &i.name
                         ),
                         AskDefaultAnswers::Yes,
-                    );
+                    )?;
                     match answer {
-                        AskDefaultAnswers::Yes => {
-                            Term::work(&format!(
-                                "Replacing data of `{}` with from new one.",
-                                &i.name
-                            ));
-                            database.set_note_content(&i.name, &i.content).unwrap();
-                            database
-                                .set_note_description(&i.name, &i.description)
-                                .unwrap();
+                        AskDefaultAnswers::Yes => Some(OnConflict::Overwrite),
+                        AskDefaultAnswers::No => {
+                            Term::warn("Skipping...");
+                            continue;
                         }
-                        AskDefaultAnswers::No => Term::warn("Skipping..."),
                     }
                 }
-                if !replace && !interactive {
-                    Term::warn(
-                        format!(
-                            "Note with name '{}' already exists in database.",
-                            &i.name.clone()
-                        )
-                        .as_str(),
-                    );
+                false => on_conflict,
+            };
+
+            match on_conflict {
+                Some(OnConflict::Overwrite) => {
+                    Term::work(&format!(
+                        "Replacing data of `{}` with from new one.",
+                        &i.name
+                    ));
+                    database.insert_note(i, OnConflict::Overwrite)?;
                 }
-            } else {
-                Term::work(format!("Adding new note: {}", &i.name.clone()).as_str());
-                database.add_note(i);
+                Some(strategy) => {
+                    let name: String = i.name.clone();
+                    let new_name: String = database.insert_note(i, strategy)?;
+                    Term::work(&format!("Adding `{}` as `{}`.", name, new_name));
+                }
+                None => Term::warn(&format!(
+                    "Note with name '{}' already exists in database.",
+                    &i.name
+                )),
             }
         }
-        Manager::write_database(database);
-        Term::success("Import finished.");
+        Ok(())
+    }
+
+    // Run plugin `kamiya-<name>` from PATH. Returns exit code of the plugin.
+    pub fn run_plugin(name: &str, args: &[OsString]) -> Result<i32, AppError> {
+        let program: String = format!("kamiya-{}", name);
+        match run_program(&program, args, &Manager::get_env_vars()) {
+            Ok(code) => Ok(code.unwrap_or(1)),
+            Err(ProcessError::ExecutableNotFound(_)) => Err(AppError::not_found(&format!(
+                "Unknown command '{}'.",
+                name
+            ))
+            .with_hint(&format!(
+                "Use `kamiya help` to see all commands, or put `{}` to PATH to add it.",
+                program
+            ))),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn plugin_api() -> Result<(), AppError> {
+        plugin::serve().map_err(|e| AppError::io("Failed to talk with plugin.", e))
+    }
+
+    pub fn completions(shell: Shell) {
+        print!("{}", completions::script(shell, &mut args()));
+    }
+
+    pub fn man(out: Option<&str>) -> Result<(), AppError> {
+        let Some(dir) = out else {
+            return man::render(args(), &mut std::io::stdout())
+                .map_err(|e| AppError::io("Failed to print manual page.", e));
+        };
+
+        let paths = man::generate_to(args(), Path::new(dir))
+            .map_err(|e| AppError::io(&format!("Failed to write manual pages to '{}'.", dir), e))?;
+        Term::success(&format!(
+            "Written {} manual pages to '{}'.",
+            paths.len(),
+            dir
+        ));
+        Ok(())
+    }
+
+    // Print names of notes if word after given words is name of note.
+    // Returns false if it's not, so shell can complete it another way.
+    pub fn complete_names(words: &[String]) -> bool {
+        if !completions::wants_name(&mut args(), words) {
+            return false;
+        }
+        // Command line being completed can use another profile or data directory.
+        let line = iter::once(String::from("kamiya")).chain(words.iter().cloned());
+        if let Ok(matches) = args().ignore_errors(true).try_get_matches_from(line) {
+            if let Some(path) = matches.get_one::<String>("data_dir") {
+                Manager::set_data_dir(path);
+            }
+            if let Some(profile) = matches.get_one::<String>("profile") {
+                Manager::set_profile(profile);
+            }
+        }
+        if Manager::validate_profile_name(&Manager::get_profile()).is_err() {
+            return false;
+        }
+
+        // Encrypted database is skipped, because completion can't ask for passphrase.
+        let path: String = Manager::get_database_path();
+        let encrypted: bool = fs::read_to_string(&path)
+            .map(|content| crypto::is_encrypted(&content))
+            .unwrap_or(false);
+        if !encrypted {
+            if let Ok(database) = Manager::load_database() {
+                for note in database.get_notes() {
+                    println!("{}", note.name);
+                }
+            }
+        }
+        true
+    }
+
+    // Print output, or send it to pager if it doesn't fit on the screen.
+    fn show(output: &str, no_pager: bool) -> Result<(), AppError> {
+        let fits = match Term::size() {
+            Some((width, height)) => Term::count_rows(output, width) < height,
+            None => true,
+        };
+
+        if !no_pager && !fits && Term::is_interactive() {
+            let mut pager: String = Manager::load_config()?.get_pager();
+            if pager.is_empty() {
+                pager = env::var("PAGER").unwrap_or_default();
+            }
+            if pager.is_empty() {
+                pager = String::from("less");
+            }
+
+            match run_pager(&pager, &format!("{}\n", output)) {
+                Ok(_) | Err(ProcessError::BadExitCode(_)) => return Ok(()),
+                Err(_) => Term::warn(&format!("Failed to launch pager `{}`.", pager)),
+            }
+        }
+
+        println!("{}", output);
+        Ok(())
     }
 }
@@ -0,0 +1,171 @@
+use crate::error::AppError;
+use clap::Command;
+use std::{collections::BTreeMap, ffi::OsString};
+
+// Find position of command in arguments, skipping program name, global options and their values.
+fn command_position(cmd: &Command, args: &[OsString]) -> Option<usize> {
+    let mut index = 1;
+    while let Some(arg) = args.get(index) {
+        let arg = arg.to_str()?;
+        let Some(long) = arg.strip_prefix("--") else {
+            return match arg.starts_with('-') {
+                true => None,
+                false => Some(index),
+            };
+        };
+        // Value can be attached to option, like `--profile=work`.
+        let takes_value = !long.contains('=')
+            && cmd.get_arguments().any(|option| {
+                option.get_long() == Some(long) && option.get_action().takes_values()
+            });
+        index += if takes_value { 2 } else { 1 };
+    }
+    None
+}
+
+// Replace alias with its command. Commands of aliases are expanded too, so aliases can
+// refer to other aliases. Built-in commands can't be replaced.
+pub fn expand(
+    cmd: &mut Command,
+    mut args: Vec<OsString>,
+    aliases: &BTreeMap<String, String>,
+) -> Result<Vec<OsString>, AppError> {
+    cmd.build();
+    let mut expanded: Vec<String> = Vec::new();
+    while let Some(index) = command_position(cmd, &args) {
+        let name: String = args[index].to_string_lossy().to_string();
+        if cmd.find_subcommand(&name).is_some() {
+            break;
+        }
+        let Some(alias) = aliases.get(&name) else {
+            break;
+        };
+
+        expanded.push(name.clone());
+        if expanded[..expanded.len() - 1].contains(&name) {
+            return Err(AppError::parse(&format!(
+                "Alias '{}' refers to itself: {}.",
+                name,
+                expanded.join(" -> ")
+            ))
+            .with_hint("Fix it with `kamiya config edit`."));
+        }
+        let words: Vec<String> = match shlex::split(alias) {
+            Some(words) if !words.is_empty() => words,
+            _ => {
+                return Err(AppError::parse(&format!(
+                    "Alias '{}' has bad command `{}`.",
+                    name, alias
+                ))
+                .with_hint("Fix it with `kamiya config edit`."))
+            }
+        };
+        args.splice(index..=index, words.into_iter().map(OsString::from));
+    }
+    Ok(args)
+}
+
+// List of aliases for help message.
+pub fn help(aliases: &BTreeMap<String, String>) -> String {
+    let width: usize = aliases
+        .keys()
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut help = String::from("Aliases:");
+    for (name, command) in aliases {
+        help.push_str(&format!("\n  {:width$}  {}", name, command, width = width));
+    }
+    help
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::args;
+
+    fn aliases(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, command)| (name.to_string(), command.to_string()))
+            .collect()
+    }
+
+    fn run(words: &[&str], aliases: &BTreeMap<String, String>) -> Result<Vec<String>, AppError> {
+        let raw: Vec<OsString> = words.iter().map(OsString::from).collect();
+        let expanded = expand(&mut args(), raw, aliases)?;
+        Ok(expanded
+            .iter()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect())
+    }
+
+    #[test]
+    fn expands_alias() {
+        let aliases = aliases(&[("t", "get \"To do\"")]);
+        assert_eq!(
+            run(&["kamiya", "t", "--no-pager"], &aliases).unwrap(),
+            ["kamiya", "get", "To do", "--no-pager"]
+        );
+    }
+
+    #[test]
+    fn expands_chain() {
+        let aliases = aliases(&[("t", "todo --no-pager"), ("todo", "get todo")]);
+        assert_eq!(
+            run(&["kamiya", "t"], &aliases).unwrap(),
+            ["kamiya", "get", "todo", "--no-pager"]
+        );
+    }
+
+    #[test]
+    fn reports_recursion() {
+        let cycle = aliases(&[("a", "b"), ("b", "a --no-pager")]);
+        let e = run(&["kamiya", "a"], &cycle).unwrap_err();
+        assert_eq!(e.to_string(), "Alias 'a' refers to itself: a -> b -> a.");
+        assert_eq!(e.exit_code(), 6);
+
+        let direct = aliases(&[("s", "s")]);
+        assert!(run(&["kamiya", "s"], &direct).is_err());
+    }
+
+    #[test]
+    fn skips_global_options() {
+        let aliases = aliases(&[("t", "get todo")]);
+        assert_eq!(
+            run(&["kamiya", "--profile", "work", "t"], &aliases).unwrap(),
+            ["kamiya", "--profile", "work", "get", "todo"]
+        );
+        assert_eq!(
+            run(&["kamiya", "--profile=work", "t"], &aliases).unwrap(),
+            ["kamiya", "--profile=work", "get", "todo"]
+        );
+        // Value of option is not a command, even if it has the name of alias.
+        assert_eq!(
+            run(&["kamiya", "--profile", "t", "list"], &aliases).unwrap(),
+            ["kamiya", "--profile", "t", "list"]
+        );
+    }
+
+    #[test]
+    fn keeps_built_in_commands() {
+        let aliases = aliases(&[("list", "get list"), ("l", "list")]);
+        assert_eq!(
+            run(&["kamiya", "list"], &aliases).unwrap(),
+            ["kamiya", "list"]
+        );
+        assert_eq!(run(&["kamiya", "l"], &aliases).unwrap(), ["kamiya", "list"]);
+        // Arguments after command are not expanded.
+        assert_eq!(
+            run(&["kamiya", "get", "l"], &aliases).unwrap(),
+            ["kamiya", "get", "l"]
+        );
+    }
+
+    #[test]
+    fn reports_bad_command() {
+        let aliases = aliases(&[("q", "get \"unclosed"), ("e", "")]);
+        assert!(run(&["kamiya", "q"], &aliases).is_err());
+        assert!(run(&["kamiya", "e"], &aliases).is_err());
+    }
+}
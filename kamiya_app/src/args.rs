@@ -1,4 +1,15 @@
 use clap::{value_parser, Arg, ArgAction, Command};
+use clap_complete::Shell;
+
+// What to do if note with the same name already exists.
+fn on_conflict(values: &[&'static str], default: &'static str) -> Arg {
+    Arg::new("on_conflict")
+        .help("What to do if note with the same name exists. `suffix` adds number to the name, like `Note (2)`.")
+        .long("on-conflict")
+        .num_args(1)
+        .default_value(default)
+        .value_parser(values.to_vec())
+}
 
 pub fn args() -> Command {
     Command::new("kamiya")
@@ -6,6 +17,33 @@ pub fn args() -> Command {
         .version(env!("CARGO_PKG_VERSION"))
         .subcommand_required(true)
         .arg_required_else_help(true)
+        // Unknown command `foo` runs plugin `kamiya-foo`.
+        .allow_external_subcommands(true)
+        .arg(
+            Arg::new("data_dir")
+                .help("Directory to store configuration and database in.")
+                .long("data-dir")
+                .global(true)
+                .num_args(1)
+                .value_parser(value_parser!(String)),
+        )
+        .arg(
+            Arg::new("profile")
+                .help("Profile to use instead of the active one.")
+                .long("profile")
+                .global(true)
+                .num_args(1)
+                .value_parser(value_parser!(String)),
+        )
+        .arg(
+            Arg::new("set")
+                .help("Set option for this run only, like `--set editor=vim`.")
+                .long("set")
+                .global(true)
+                .value_name("KEY=VALUE")
+                .action(ArgAction::Append)
+                .value_parser(value_parser!(String)),
+        )
         .subcommands([
             Command::new("take").about("Create a new note.").args([
                 Arg::new("content")
@@ -29,6 +67,12 @@ pub fn args() -> Command {
                     .required(false)
                     .default_value("")
                     .value_parser(value_parser!(String)),
+                Arg::new("secret")
+                    .short('s')
+                    .long("secret")
+                    .help("Encrypt content of the note with passphrase.")
+                    .required(false)
+                    .action(ArgAction::SetTrue),
             ]),
             Command::new("add")
                 .about("Save content of file as note.")
@@ -47,6 +91,7 @@ pub fn args() -> Command {
                         .required(false)
                         .default_value("")
                         .value_parser(clap::value_parser!(String)),
+                    on_conflict(&["fail", "overwrite", "suffix"], "fail"),
                 ]),
             Command::new("desc")
                 .about("Add description to note.")
@@ -79,41 +124,38 @@ pub fn args() -> Command {
                     .required(true)
                     .help("New note name.")
                     .value_parser(value_parser!(String)),
+                on_conflict(&["fail", "overwrite", "suffix"], "fail"),
             ]),
             Command::new("get")
                 .about("Get the contents of a note from the storage.")
-                .arg(
+                .args([
                     Arg::new("name")
                         .help("Name of note to read.")
                         .num_args(1)
                         .required(true)
                         .value_parser(clap::value_parser!(String)),
-                ),
-            Command::new("open").about("Open note in your editor.").arg(
-                Arg::new("name")
-                    .help("Name of note to open.")
-                    .num_args(1)
-                    .required(true)
-                    .value_parser(clap::value_parser!(String)),
-            ),
-            Command::new("editor")
-                .about("Set editor for editing notes or check which are using now.")
-                .arg(
-                    Arg::new("editor")
-                        .help("Editor to use")
-                        .value_parser(value_parser!(String))
-                        .default_value("")
-                        .num_args(1),
-                ),
-            Command::new("template")
-                .about("Template for new notes.")
-                .arg(
-                    Arg::new("template")
-                        .help("New template.")
-                        .value_parser(value_parser!(String))
-                        .default_value("")
-                        .num_args(1),
-                ),
+                    Arg::new("no_pager")
+                        .help("Print output directly instead of using pager.")
+                        .long("no-pager")
+                        .required(false)
+                        .action(ArgAction::SetTrue),
+                ]),
+            Command::new("open")
+                .about("Open note in your editor.")
+                .args([
+                    Arg::new("name")
+                        .help("Name of note to open.")
+                        .num_args(1)
+                        .required(true)
+                        .value_parser(clap::value_parser!(String)),
+                    Arg::new("line")
+                        .help("Line to open note at. Used for `{line}` in editor command.")
+                        .short('l')
+                        .long("line")
+                        .num_args(1)
+                        .default_value("1")
+                        .value_parser(clap::value_parser!(usize)),
+                ]),
             Command::new("delete")
                 .about("Delete a note from the storage.")
                 .arg(
@@ -125,14 +167,27 @@ pub fn args() -> Command {
                 ),
             Command::new("search")
                 .about("Search for notes by name.")
-                .arg(
+                .args([
                     Arg::new("pattern")
                         .help("Part or full name of note.")
                         .num_args(1)
                         .required(true)
                         .value_parser(clap::value_parser!(String)),
+                    Arg::new("no_pager")
+                        .help("Print output directly instead of using pager.")
+                        .long("no-pager")
+                        .required(false)
+                        .action(ArgAction::SetTrue),
+                ]),
+            Command::new("list")
+                .about("Get a list of the notes in the storage.")
+                .arg(
+                    Arg::new("no_pager")
+                        .help("Print output directly instead of using pager.")
+                        .long("no-pager")
+                        .required(false)
+                        .action(ArgAction::SetTrue),
                 ),
-            Command::new("list").about("Get a list of the notes in the storage."),
             Command::new("save")
                 .about("Save note from storage as file.")
                 .args([
@@ -150,14 +205,28 @@ pub fn args() -> Command {
                         .num_args(1)
                         .value_parser(clap::value_parser!(String)),
                 ]),
-            Command::new("export").about("Export database.").arg(
+            Command::new("export").about("Export database.").args([
                 Arg::new("path")
-                    .help("Path where database will be saved.")
+                    .help("Path where database will be saved. Directory for markdown and HTML.")
                     .short('p')
                     .long("path")
-                    .value_parser(value_parser!(String))
-                    .default_value("kamiya_exported.json"),
-            ),
+                    .num_args(1)
+                    .value_parser(value_parser!(String)),
+                Arg::new("format")
+                    .help("Format of exported notes.")
+                    .short('f')
+                    .long("format")
+                    .num_args(1)
+                    .value_parser(["json", "markdown", "html"])
+                    .default_value("json"),
+                Arg::new("bundle")
+                    .help("Pack notes into .tar.gz or .zip bundle with checksums.")
+                    .short('b')
+                    .long("bundle")
+                    .num_args(1)
+                    .conflicts_with_all(["path", "format"])
+                    .value_parser(value_parser!(String)),
+            ]),
             Command::new("import")
                 .about("Import notes from new database.")
                 .args([
@@ -168,18 +237,208 @@ pub fn args() -> Command {
                         .num_args(1)
                         .default_value("kamiya_exported.yml")
                         .value_parser(clap::value_parser!(String)),
+                    Arg::new("from_dir")
+                        .help("Import every file in directory as note.")
+                        .long("from-dir")
+                        .num_args(1)
+                        .conflicts_with("filename")
+                        .value_parser(clap::value_parser!(String)),
+                    Arg::new("obsidian")
+                        .help("Import notes from Obsidian vault.")
+                        .long("obsidian")
+                        .num_args(1)
+                        .conflicts_with_all(["filename", "from_dir"])
+                        .value_parser(clap::value_parser!(String)),
+                    Arg::new("bundle")
+                        .help("Import notes from .tar.gz or .zip bundle.")
+                        .short('b')
+                        .long("bundle")
+                        .num_args(1)
+                        .conflicts_with_all(["filename", "from_dir", "obsidian"])
+                        .value_parser(clap::value_parser!(String)),
+                    Arg::new("glob")
+                        .help("Pattern of files to import from directory.")
+                        .long("glob")
+                        .num_args(1)
+                        .requires("from_dir")
+                        .default_value("*.{md,markdown,txt}")
+                        .value_parser(clap::value_parser!(String)),
                     Arg::new("replace")
-                        .help("Replace if note with the same name exists or not.")
+                        .help("Replace if note with the same name exists. Same as `--on-conflict overwrite`.")
                         .short('r')
                         .long("replace")
                         .required(false)
+                        .conflicts_with("on_conflict")
                         .action(ArgAction::SetTrue),
                     Arg::new("interactive")
                         .help("Ask what to do if note with same name exists.")
                         .short('i')
                         .long("interactive")
                         .required(false)
+                        .conflicts_with("on_conflict")
                         .action(ArgAction::SetTrue),
+                    on_conflict(&["skip", "fail", "overwrite", "suffix"], "skip"),
+                ]),
+            Command::new("lock")
+                .about("Make note secret by encrypting its content with passphrase.")
+                .arg(
+                    Arg::new("name")
+                        .help("Name of note.")
+                        .num_args(1)
+                        .required(true)
+                        .value_parser(clap::value_parser!(String)),
+                ),
+            Command::new("unlock")
+                .about("Decrypt secret note and store it as plain text.")
+                .arg(
+                    Arg::new("name")
+                        .help("Name of note.")
+                        .num_args(1)
+                        .required(true)
+                        .value_parser(clap::value_parser!(String)),
+                ),
+            Command::new("profile")
+                .about("Manage profiles with separate databases.")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommands([
+                    Command::new("create").about("Create new profile.").arg(
+                        Arg::new("name")
+                            .help("Name of profile.")
+                            .num_args(1)
+                            .required(true)
+                            .value_parser(value_parser!(String)),
+                    ),
+                    Command::new("list").about("Get a list of profiles."),
+                    Command::new("use").about("Switch to another profile.").arg(
+                        Arg::new("name")
+                            .help("Name of profile.")
+                            .num_args(1)
+                            .required(true)
+                            .value_parser(value_parser!(String)),
+                    ),
+                    Command::new("delete")
+                        .about("Delete profile with all its notes.")
+                        .args([
+                            Arg::new("name")
+                                .help("Name of profile.")
+                                .num_args(1)
+                                .required(true)
+                                .value_parser(value_parser!(String)),
+                            Arg::new("yes")
+                                .help("Don't ask for confirmation.")
+                                .short('y')
+                                .long("yes")
+                                .action(ArgAction::SetTrue),
+                        ]),
+                ]),
+            Command::new("config")
+                .about("Manage configuration.")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommands([
+                    Command::new("get").about("Get value of option.").arg(
+                        Arg::new("key")
+                            .help("Name of option, like `editor` or `editors.md`.")
+                            .num_args(1)
+                            .required(true)
+                            .value_parser(value_parser!(String)),
+                    ),
+                    Command::new("set")
+                        .about("Change option in user configuration.")
+                        .args([
+                            Arg::new("key")
+                                .help("Name of option, like `editor` or `editors.md`.")
+                                .num_args(1)
+                                .required(true)
+                                .value_parser(value_parser!(String)),
+                            Arg::new("value")
+                                .help("New value.")
+                                .num_args(1)
+                                .required(true)
+                                .value_parser(value_parser!(String)),
+                        ]),
+                    Command::new("list").about("Get a list of options with their values."),
+                    Command::new("edit").about("Open user configuration in your editor."),
+                    Command::new("check").about("Check configuration files for problems."),
+                    Command::new("path")
+                        .about("Get path to user configuration.")
+                        .arg(
+                            Arg::new("all")
+                                .help("Show paths to all configuration files.")
+                                .long("all")
+                                .action(ArgAction::SetTrue),
+                        ),
                 ]),
+            Command::new("doctor")
+                .about("Check configuration and database for problems.")
+                .arg(
+                    Arg::new("fix")
+                        .help("Repair problems that can be fixed safely. Backup is made before.")
+                        .long("fix")
+                        .action(ArgAction::SetTrue),
+                ),
+            Command::new("encrypt")
+                .about("Encrypt database with passphrase.")
+                .arg(
+                    Arg::new("yes")
+                        .help("Don't ask for confirmation if data directory is git repository.")
+                        .short('y')
+                        .long("yes")
+                        .action(ArgAction::SetTrue),
+                ),
+            Command::new("decrypt").about("Decrypt database and store it as plain text."),
+            Command::new("git")
+                .about("Keep history of notes with git.")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommands([Command::new("init").about(
+                    "Turn data directory into git repository. Every change is committed after that.",
+                )]),
+            Command::new("log").about("Show history of changes.").args([
+                Arg::new("limit")
+                    .help("Show only the last changes.")
+                    .short('n')
+                    .long("limit")
+                    .num_args(1)
+                    .value_parser(value_parser!(usize)),
+                Arg::new("patch")
+                    .help("Show changes of database for every commit.")
+                    .short('p')
+                    .long("patch")
+                    .action(ArgAction::SetTrue),
+                Arg::new("no_pager")
+                    .help("Print output directly instead of using pager.")
+                    .long("no-pager")
+                    .required(false)
+                    .action(ArgAction::SetTrue),
+            ]),
+            Command::new("completions")
+                .about("Generate completion script for your shell.")
+                .arg(
+                    Arg::new("shell")
+                        .help("Shell to generate script for.")
+                        .required(true)
+                        .value_parser(value_parser!(Shell)),
+                ),
+            Command::new("man")
+                .about("Generate manual pages. Without `--out` prints page of `kamiya` only.")
+                .arg(
+                    Arg::new("out")
+                        .help("Directory to write pages of Kamiya and every command to.")
+                        .short('o')
+                        .long("out")
+                        .num_args(1)
+                        .value_parser(value_parser!(String)),
+                ),
+            Command::new("plugin-api")
+                .about("Answer JSON requests from stdin. Used by plugins to read and write notes."),
+            // Used by completion scripts to get names of notes.
+            Command::new("__complete-names").hide(true).arg(
+                Arg::new("words")
+                    .num_args(0..)
+                    .allow_hyphen_values(true)
+                    .value_parser(value_parser!(String)),
+            ),
         ])
 }
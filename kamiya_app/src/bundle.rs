@@ -0,0 +1,362 @@
+use crate::markdown;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use kamiya_database::Note;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, Read, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+const MANIFEST: &str = "manifest.json";
+const FORMAT: &str = "kamiya-bundle";
+const VERSION: u32 = 1;
+
+// Description of the bundle and every note inside it.
+#[derive(Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: String,
+    pub version: u32,
+    pub created_by: String,
+    pub notes: Vec<ManifestEntry>,
+}
+
+// Note metadata and path to its content inside bundle.
+#[derive(Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub secret: bool,
+    #[serde(default)]
+    pub modified: u64,
+    pub file: String,
+    pub sha256: String,
+}
+
+// Errors which may occur.
+#[derive(Debug)]
+pub enum BundleError {
+    Io(io::Error),
+    BadArchive,
+    BadManifest,
+    UnsupportedVersion(u32),
+    MissingFile(String),
+    ChecksumMismatch(String),
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BundleError::Io(e) => write!(f, "Failed to access bundle. Error: {}", e),
+            BundleError::BadArchive => write!(f, "Bundle is not a valid archive."),
+            BundleError::BadManifest => write!(f, "Bundle has bad formatted manifest."),
+            BundleError::UnsupportedVersion(version) => write!(
+                f,
+                "Bundle version {} is not supported. Update Kamiya to import it.",
+                version
+            ),
+            BundleError::MissingFile(file) => write!(f, "Bundle is missing file '{}'.", file),
+            BundleError::ChecksumMismatch(file) => write!(
+                f,
+                "Checksum of '{}' doesn't match. Bundle is corrupted.",
+                file
+            ),
+        }
+    }
+}
+
+impl Error for BundleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BundleError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BundleError {
+    fn from(e: io::Error) -> Self {
+        BundleError::Io(e)
+    }
+}
+
+impl From<zip::result::ZipError> for BundleError {
+    fn from(e: zip::result::ZipError) -> Self {
+        match e {
+            zip::result::ZipError::Io(e) => BundleError::Io(e),
+            _ => BundleError::BadArchive,
+        }
+    }
+}
+
+// Bundles with `.zip` extension are zip archives, others are gzipped tarballs.
+fn is_zip(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case("zip"))
+        .unwrap_or(false)
+}
+
+pub fn checksum(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// Pack notes into compressed archive with manifest.
+pub fn write_bundle(path: &Path, notes: &[Note]) -> Result<(), BundleError> {
+    let mut taken: HashSet<String> = HashSet::new();
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut manifest = Manifest {
+        format: String::from(FORMAT),
+        version: VERSION,
+        created_by: format!("kamiya {}", env!("CARGO_PKG_VERSION")),
+        notes: Vec::new(),
+    };
+
+    for note in notes {
+        let file = format!("notes/{}.md", markdown::unique_slug(&note.name, &mut taken));
+        let content = note.content.as_bytes().to_vec();
+        manifest.notes.push(ManifestEntry {
+            name: note.name.clone(),
+            description: note.description.clone(),
+            tags: note.tags.clone(),
+            secret: note.secret,
+            modified: note.modified,
+            file: file.clone(),
+            sha256: checksum(&content),
+        });
+        files.push((file, content));
+    }
+    let manifest = serde_json::to_vec_pretty(&manifest).unwrap();
+    files.insert(0, (String::from(MANIFEST), manifest));
+    write_files(path, files)
+}
+
+// Write files into archive. Format is chosen by extension of the path.
+fn write_files(path: &Path, files: Vec<(String, Vec<u8>)>) -> Result<(), BundleError> {
+    let archive = File::create(path)?;
+    if is_zip(path) {
+        let mut zip = ZipWriter::new(archive);
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        for (name, data) in files {
+            zip.start_file(name, options)?;
+            zip.write_all(&data)?;
+        }
+        zip.finish()?;
+    } else {
+        let mtime = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let mut tar = tar::Builder::new(GzEncoder::new(archive, Compression::default()));
+        for (name, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(mtime);
+            tar.append_data(&mut header, name, data.as_slice())?;
+        }
+        tar.into_inner()?.finish()?;
+    }
+    Ok(())
+}
+
+fn read_files(path: &Path) -> Result<HashMap<String, Vec<u8>>, BundleError> {
+    let archive = File::open(path)?;
+    let mut files: HashMap<String, Vec<u8>> = HashMap::new();
+
+    if is_zip(path) {
+        let mut zip = ZipArchive::new(archive)?;
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i)?;
+            if file.is_dir() {
+                continue;
+            }
+            let mut data = Vec::new();
+            file.read_to_end(&mut data)?;
+            files.insert(file.name().to_string(), data);
+        }
+    } else {
+        let mut tar = tar::Archive::new(GzDecoder::new(archive));
+        let entries = tar.entries().map_err(|_| BundleError::BadArchive)?;
+        for entry in entries {
+            let mut entry = entry.map_err(|_| BundleError::BadArchive)?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = entry.path()?.to_string_lossy().to_string();
+            let mut data = Vec::new();
+            entry
+                .read_to_end(&mut data)
+                .map_err(|_| BundleError::BadArchive)?;
+            files.insert(name, data);
+        }
+    }
+    Ok(files)
+}
+
+// Unpack notes from bundle, verifying checksums of every note.
+pub fn read_bundle(path: &Path) -> Result<Vec<Note>, BundleError> {
+    let files = read_files(path)?;
+    let manifest: Manifest = match files.get(MANIFEST) {
+        Some(data) => serde_json::from_slice(data).map_err(|_| BundleError::BadManifest)?,
+        None => return Err(BundleError::MissingFile(String::from(MANIFEST))),
+    };
+    if manifest.format != FORMAT {
+        return Err(BundleError::BadManifest);
+    }
+    if manifest.version > VERSION {
+        return Err(BundleError::UnsupportedVersion(manifest.version));
+    }
+
+    let mut notes: Vec<Note> = Vec::new();
+    for entry in manifest.notes {
+        let data = match files.get(&entry.file) {
+            Some(data) => data,
+            None => return Err(BundleError::MissingFile(entry.file)),
+        };
+        if checksum(data) != entry.sha256 {
+            return Err(BundleError::ChecksumMismatch(entry.file));
+        }
+        let content = match String::from_utf8(data.clone()) {
+            Ok(content) => content,
+            Err(_) => return Err(BundleError::BadArchive),
+        };
+        notes.push(Note {
+            id: String::new(),
+            name: entry.name,
+            content,
+            description: entry.description,
+            tags: entry.tags,
+            secret: entry.secret,
+            modified: entry.modified,
+        });
+    }
+    Ok(notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kamiya_utils::tempfile::TempFile;
+
+    fn notes() -> Vec<Note> {
+        vec![
+            Note {
+                name: String::from("Shopping list"),
+                content: String::from("- milk\n- bread\n"),
+                description: String::from("Weekly"),
+                tags: vec![String::from("home")],
+                modified: 1700000000,
+                ..Note::default()
+            },
+            // Same slug as the first note, so file gets another name.
+            Note {
+                name: String::from("shopping list"),
+                content: String::from("Ключ: значение"),
+                secret: true,
+                ..Note::default()
+            },
+        ]
+    }
+
+    fn round_trip(extension: &str) {
+        let file = TempFile::new(Some(extension)).unwrap();
+        let path = file.get_path();
+        write_bundle(Path::new(&path), &notes()).unwrap();
+
+        let read = read_bundle(Path::new(&path)).unwrap();
+        assert_eq!(read.len(), 2);
+        for (read, note) in read.iter().zip(notes()) {
+            assert_eq!(read.name, note.name);
+            assert_eq!(read.content, note.content);
+            assert_eq!(read.description, note.description);
+            assert_eq!(read.tags, note.tags);
+            assert_eq!(read.secret, note.secret);
+            assert_eq!(read.modified, note.modified);
+        }
+    }
+
+    #[test]
+    fn tar_round_trip() {
+        round_trip("tar.gz");
+    }
+
+    #[test]
+    fn zip_round_trip() {
+        round_trip("zip");
+    }
+
+    fn checksum_mismatch(extension: &str) {
+        let manifest = Manifest {
+            format: String::from(FORMAT),
+            version: VERSION,
+            created_by: String::from("test"),
+            notes: vec![ManifestEntry {
+                name: String::from("a"),
+                description: String::new(),
+                tags: Vec::new(),
+                secret: false,
+                modified: 0,
+                file: String::from("notes/a.md"),
+                sha256: checksum(b"original"),
+            }],
+        };
+        let files = vec![
+            (
+                String::from(MANIFEST),
+                serde_json::to_vec(&manifest).unwrap(),
+            ),
+            (String::from("notes/a.md"), b"changed".to_vec()),
+        ];
+        let file = TempFile::new(Some(extension)).unwrap();
+        let path = file.get_path();
+        write_files(Path::new(&path), files).unwrap();
+
+        let result = read_bundle(Path::new(&path));
+        assert!(matches!(result, Err(BundleError::ChecksumMismatch(file)) if file == "notes/a.md"));
+    }
+
+    #[test]
+    fn tar_checksum_mismatch() {
+        checksum_mismatch("tar.gz");
+    }
+
+    #[test]
+    fn zip_checksum_mismatch() {
+        checksum_mismatch("zip");
+    }
+
+    #[test]
+    fn missing_manifest() {
+        let file = TempFile::new(Some("zip")).unwrap();
+        let path = file.get_path();
+        write_files(Path::new(&path), vec![(String::from("a.md"), Vec::new())]).unwrap();
+        assert!(matches!(
+            read_bundle(Path::new(&path)),
+            Err(BundleError::MissingFile(file)) if file == MANIFEST
+        ));
+    }
+
+    #[test]
+    fn not_an_archive() {
+        let file = TempFile::new(Some("zip")).unwrap();
+        let path = file.get_path();
+        std::fs::write(&path, "not a zip").unwrap();
+        assert!(matches!(
+            read_bundle(Path::new(&path)),
+            Err(BundleError::BadArchive)
+        ));
+    }
+}
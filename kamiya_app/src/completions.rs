@@ -0,0 +1,253 @@
+use clap::{Arg, Command};
+use clap_complete::Shell;
+
+// Arguments which take name of note, as pairs of subcommand and argument ID.
+const NAME_ARGS: [(&str, &str); 8] = [
+    ("get", "name"),
+    ("open", "name"),
+    ("delete", "name"),
+    ("desc", "name"),
+    ("save", "name"),
+    ("rename", "old_name"),
+    ("lock", "name"),
+    ("unlock", "name"),
+];
+
+// Shell code which asks `kamiya __complete-names` for note names before falling back
+// to generated completion. The hidden command fails if the word is not a note name.
+// Words starting with `-` are always completed as options.
+const BASH_NAMES: &str = r#"
+_kamiya_names() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    local names name
+    if [[ "${cur}" != -* ]] &&
+        names="$(kamiya __complete-names -- "${COMP_WORDS[@]:1:COMP_CWORD-1}" 2>/dev/null)"; then
+        COMPREPLY=()
+        while IFS= read -r name; do
+            if [[ -n "${name}" && "${name}" == "${cur}"* ]]; then
+                COMPREPLY+=("$(printf '%q' "${name}")")
+            fi
+        done <<< "${names}"
+        return 0
+    fi
+    _kamiya "$@"
+}
+
+complete -F _kamiya_names -o bashdefault -o default kamiya
+"#;
+
+const ZSH_NAMES: &str = r#"_kamiya() {
+    local names
+    if [[ "${words[CURRENT]}" != -* ]] &&
+        names="$(kamiya __complete-names -- "${(@)words[2,CURRENT-1]}" 2>/dev/null)"; then
+        local -a candidates
+        candidates=("${(@f)names}")
+        [[ -n "${names}" ]] && compadd -a candidates
+        return
+    fi
+    _kamiya_generated "$@"
+}
+
+if [ "$funcstack[1]" = "_kamiya" ]; then"#;
+
+const FISH_NAMES: &str = r#"
+complete -c kamiya -f -n 'not string match -q -- "-*" (commandline -ct); and kamiya __complete-names -- (commandline -opc)[2..-1] >/dev/null 2>&1' -a '(kamiya __complete-names -- (commandline -opc)[2..-1] 2>/dev/null)'
+"#;
+
+const ELVISH_NAMES: &str = r#"
+var kamiya-generated = $edit:completion:arg-completer[kamiya]
+set edit:completion:arg-completer[kamiya] = {|@words|
+    var names = $nil
+    if (not (str:has-prefix $words[-1] -)) {
+        try {
+            set names = [(kamiya __complete-names -- (all $words[1..-1]) 2>/dev/null)]
+        } catch e { }
+    }
+    if (eq $names $nil) {
+        $kamiya-generated $@words
+    } else {
+        all $names
+    }
+}
+"#;
+
+const POWERSHELL_NAMES: &str = r#"param($wordToComplete, $commandAst, $cursorPosition)
+
+    $words = @($commandAst.CommandElements | Select-Object -Skip 1 |
+        Where-Object { $_.Extent.EndOffset -lt $cursorPosition } | ForEach-Object { $_.ToString() })
+    $names = & kamiya __complete-names -- @words 2>$null
+    if (-not $wordToComplete.StartsWith('-') -and $LASTEXITCODE -eq 0) {
+        $names | Where-Object { $_ -like "$wordToComplete*" } | ForEach-Object {
+            [System.Management.Automation.CompletionResult]::new("'$_'", $_, 'ParameterValue', $_)
+        }
+        return
+    }
+"#;
+
+// Generate completion script for shell, with completion of note names.
+pub fn script(shell: Shell, cmd: &mut Command) -> String {
+    let mut buffer: Vec<u8> = Vec::new();
+    clap_complete::generate(shell, cmd, "kamiya", &mut buffer);
+    let script = String::from_utf8_lossy(&buffer).to_string();
+
+    match shell {
+        Shell::Bash => script + BASH_NAMES,
+        // Generated function is renamed, so autoloaded `_kamiya` always completes names first.
+        Shell::Zsh => script
+            .replacen("\n_kamiya() {", "\n_kamiya_generated() {", 1)
+            .replacen("if [ \"$funcstack[1]\" = \"_kamiya\" ]; then", ZSH_NAMES, 1),
+        Shell::Fish => script + FISH_NAMES,
+        Shell::Elvish => script + ELVISH_NAMES,
+        Shell::PowerShell => script.replacen(
+            "param($wordToComplete, $commandAst, $cursorPosition)\n",
+            POWERSHELL_NAMES,
+            1,
+        ),
+        _ => script,
+    }
+}
+
+fn takes_value(arg: &Arg) -> bool {
+    arg.get_action().takes_values()
+}
+
+fn is_name_arg(command: &Command, arg: &Arg) -> bool {
+    NAME_ARGS
+        .iter()
+        .any(|(name, id)| command.get_name() == *name && arg.get_id() == *id)
+}
+
+// Check if word after given words is name of note. Words don't include program name.
+pub fn wants_name(cmd: &mut Command, words: &[String]) -> bool {
+    cmd.build();
+    let mut current: &Command = cmd;
+    let mut is_root = true;
+    let mut positionals = 0;
+    let mut pending: Option<&Arg> = None;
+
+    for word in words {
+        if pending.take().is_some() {
+            continue;
+        }
+        let arg = if let Some(long) = word.strip_prefix("--") {
+            if long.is_empty() || long.contains('=') {
+                continue;
+            }
+            current
+                .get_arguments()
+                .find(|arg| arg.get_long() == Some(long))
+        } else if let Some(short) = word.strip_prefix('-').filter(|short| !short.is_empty()) {
+            // Value can be attached to short flag, like `-nName`.
+            match short.chars().count() {
+                1 => current
+                    .get_arguments()
+                    .find(|arg| arg.get_short() == short.chars().next()),
+                _ => continue,
+            }
+        } else if is_root {
+            match current.find_subcommand(word) {
+                Some(subcommand) => {
+                    current = subcommand;
+                    is_root = false;
+                    continue;
+                }
+                None => return false,
+            }
+        } else {
+            positionals += 1;
+            continue;
+        };
+        pending = arg.filter(|arg| takes_value(arg));
+    }
+
+    match pending {
+        Some(arg) => is_name_arg(current, arg),
+        None if is_root => false,
+        None => current
+            .get_positionals()
+            .nth(positionals)
+            .is_some_and(|arg| is_name_arg(current, arg)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::args;
+
+    fn wants(words: &[&str]) -> bool {
+        let words: Vec<String> = words.iter().map(|word| word.to_string()).collect();
+        wants_name(&mut args(), &words)
+    }
+
+    #[test]
+    fn every_script_completes_names() {
+        for shell in [
+            Shell::Bash,
+            Shell::Zsh,
+            Shell::Fish,
+            Shell::Elvish,
+            Shell::PowerShell,
+        ] {
+            let script = script(shell, &mut args());
+            assert!(script.contains("kamiya __complete-names --"), "{}", shell);
+        }
+    }
+
+    #[test]
+    fn zsh_script_keeps_generated_completion() {
+        let script = script(Shell::Zsh, &mut args());
+        assert!(script.contains("\n_kamiya_generated() {"));
+        assert!(script.contains("_kamiya_generated \"$@\""));
+        assert_eq!(script.matches("\n_kamiya() {").count(), 1);
+    }
+
+    #[test]
+    fn powershell_script_checks_names_first() {
+        let script = script(Shell::PowerShell, &mut args());
+        let names = script.find("__complete-names").unwrap();
+        let generated = script.find("CompletionResult]::new('get'").unwrap();
+        assert!(names < generated);
+    }
+
+    #[test]
+    fn name_after_subcommand() {
+        assert!(wants(&["get"]));
+        assert!(wants(&["delete"]));
+        assert!(!wants(&["get", "Note"]));
+        assert!(!wants(&["take"]));
+    }
+
+    #[test]
+    fn value_flags_are_skipped() {
+        assert!(wants(&["open", "--line", "5"]));
+        assert!(wants(&["open", "-l", "5"]));
+        assert!(wants(&["open", "--line=5"]));
+        assert!(wants(&["get", "--profile", "work"]));
+        assert!(wants(&["get", "--no-pager"]));
+        assert!(!wants(&["open", "--line"]));
+    }
+
+    #[test]
+    fn value_attached_to_short_flag() {
+        assert!(wants(&["open", "-l5"]));
+        assert!(!wants(&["rename", "-oName"]));
+    }
+
+    #[test]
+    fn rename_completes_old_name_only() {
+        assert!(wants(&["rename", "-o"]));
+        assert!(wants(&["rename", "--old"]));
+        assert!(!wants(&["rename", "-n"]));
+        assert!(!wants(&["rename", "--new"]));
+        assert!(!wants(&["rename", "-o", "Note"]));
+    }
+
+    #[test]
+    fn nothing_at_root_level() {
+        assert!(!wants(&[]));
+        assert!(!wants(&["--profile", "work"]));
+        assert!(!wants(&["--profile"]));
+        assert!(!wants(&["unknown"]));
+    }
+}
@@ -0,0 +1,182 @@
+use kamiya_config::{self as config, ConfigError};
+use kamiya_database::{self as database, Note};
+use std::{
+    collections::HashSet,
+    fs,
+    io::Error,
+    path::{Path, PathBuf},
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+
+// Problem found by doctor. Fixable problems are repaired with `--fix`.
+pub struct Problem {
+    pub subject: String,
+    pub message: String,
+    pub fixable: bool,
+}
+
+impl Problem {
+    pub fn new(subject: &str, message: &str, fixable: bool) -> Self {
+        Problem {
+            subject: subject.to_string(),
+            message: message.to_string(),
+            fixable,
+        }
+    }
+}
+
+// Check configuration file. Broken configuration must be fixed by user.
+pub fn check_config(path: &str, content: &str) -> Vec<Problem> {
+    let value = match config::parse(content) {
+        Ok(value) => value,
+        Err(e) => return vec![Problem::new(path, &e.to_string(), false)],
+    };
+
+    let mut problems: Vec<Problem> = Vec::new();
+    for problem in config::validate(&value) {
+        let message = match problem {
+            ConfigError::UnknownKey(_) => format!("{} It will be ignored.", problem),
+            _ => problem.to_string(),
+        };
+        problems.push(Problem::new(path, &message, false));
+    }
+    if let Some(toml::Value::String(template)) = config::get_key(&value, "options.name_template") {
+        if !template.contains("&i") {
+            problems.push(Problem::new(path, "Template must contain `&i`.", false));
+        }
+    }
+    problems
+}
+
+fn has_invalid_chars(name: &str) -> bool {
+    name.chars().any(char::is_control) || name.trim() != name
+}
+
+// Name used to find duplicates. With `ignore_case` names differing only in case are duplicates.
+fn name_key(name: &str, ignore_case: bool) -> String {
+    match ignore_case {
+        true => database::fold_name(name),
+        false => name.to_string(),
+    }
+}
+
+// Find notes with empty, duplicate or badly formed names.
+pub fn check_names(notes: &[Note], ignore_case: bool) -> Vec<Problem> {
+    let mut problems: Vec<Problem> = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+    for (index, note) in notes.iter().enumerate() {
+        let subject = format!("Note #{}", index + 1);
+        if note.name.trim().is_empty() {
+            problems.push(Problem::new(&subject, "Name is empty.", true));
+        } else if has_invalid_chars(&note.name) {
+            problems.push(Problem::new(
+                &subject,
+                &format!(
+                    "Name {:?} has control characters or spaces around it.",
+                    note.name
+                ),
+                true,
+            ));
+        }
+        if !note.name.is_empty() && !seen.insert(name_key(&note.name, ignore_case)) {
+            problems.push(Problem::new(
+                &subject,
+                &format!("Name '{}' is used by another note.", note.name),
+                true,
+            ));
+        }
+    }
+    problems
+}
+
+// Give every note valid unique name. Empty names are generated from template.
+// Returns number of renamed notes.
+pub fn fix_names(notes: &mut [Note], template: &str, ignore_case: bool) -> usize {
+    let mut taken: HashSet<String> = HashSet::new();
+    let mut renamed = 0;
+    for (index, note) in notes.iter_mut().enumerate() {
+        let cleaned: String = note
+            .name
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect::<String>()
+            .trim()
+            .to_string();
+        let base = match cleaned.is_empty() {
+            true => template.replace("&i", &(index + 1).to_string()),
+            false => cleaned,
+        };
+
+        let mut name = base.clone();
+        let mut counter = 2;
+        while taken.contains(&name_key(&name, ignore_case)) {
+            name = format!("{} ({})", base, counter);
+            counter += 1;
+        }
+        if name != note.name {
+            note.name = name.clone();
+            renamed += 1;
+        }
+        taken.insert(name_key(&name, ignore_case));
+    }
+    renamed
+}
+
+// Check that file can be read and written, and that private files are hidden from other users.
+pub fn check_permissions(path: &str, private: bool) -> Vec<Problem> {
+    let mut problems: Vec<Problem> = Vec::new();
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return problems,
+    };
+
+    if fs::File::open(path).is_err() {
+        problems.push(Problem::new(path, "File can't be read.", false));
+    }
+    if metadata.permissions().readonly() {
+        problems.push(Problem::new(path, "File is read-only.", true));
+    }
+    #[cfg(unix)]
+    if private && metadata.permissions().mode() & 0o077 != 0 {
+        problems.push(Problem::new(
+            path,
+            "File can be accessed by other users.",
+            true,
+        ));
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+    problems
+}
+
+// Make file writable by owner, and accessible only by owner if it's private.
+pub fn fix_permissions(path: &str, private: bool) -> Result<(), Error> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    #[cfg(unix)]
+    {
+        let mut mode = permissions.mode() | 0o600;
+        if private {
+            mode &= !0o077;
+        }
+        permissions.set_mode(mode);
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = private;
+        permissions.set_readonly(false);
+    }
+    fs::set_permissions(path, permissions)
+}
+
+// Move file into directory. Works across file systems.
+pub fn move_file(path: &Path, dir: &Path) -> Result<PathBuf, Error> {
+    fs::create_dir_all(dir)?;
+    let target = dir.join(path.file_name().unwrap_or_default());
+    if fs::rename(path, &target).is_err() {
+        fs::copy(path, &target)?;
+        fs::remove_file(path)?;
+    }
+    Ok(target)
+}
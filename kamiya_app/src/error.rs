@@ -0,0 +1,142 @@
+use crate::bundle::BundleError;
+use kamiya_config::ConfigError;
+use kamiya_database::DatabaseError;
+use kamiya_utils::{crypto::CryptoError, proc::ProcessError};
+use std::{error::Error, fmt, io};
+
+// Kind of the error. Every kind has its own exit code.
+#[derive(Debug, Clone, Copy)]
+pub enum ErrorKind {
+    General,
+    NotFound,
+    Conflict,
+    Io,
+    Parse,
+    Editor,
+    Hook,
+}
+
+// Error of any command, with message for user and optional hint how to fix it.
+#[derive(Debug)]
+pub struct AppError {
+    kind: ErrorKind,
+    message: String,
+    hint: Option<String>,
+}
+
+impl AppError {
+    pub fn new(kind: ErrorKind, message: &str) -> Self {
+        AppError {
+            kind,
+            message: message.to_string(),
+            hint: None,
+        }
+    }
+
+    pub fn general(message: &str) -> Self {
+        Self::new(ErrorKind::General, message)
+    }
+
+    pub fn not_found(message: &str) -> Self {
+        Self::new(ErrorKind::NotFound, message)
+    }
+
+    pub fn conflict(message: &str) -> Self {
+        Self::new(ErrorKind::Conflict, message)
+    }
+
+    pub fn parse(message: &str) -> Self {
+        Self::new(ErrorKind::Parse, message)
+    }
+
+    pub fn editor(message: &str) -> Self {
+        Self::new(ErrorKind::Editor, message)
+    }
+
+    pub fn hook(message: &str) -> Self {
+        Self::new(ErrorKind::Hook, message)
+    }
+
+    // I/O error with description of what was going on.
+    pub fn io(context: &str, e: io::Error) -> Self {
+        Self::new(ErrorKind::Io, &format!("{} Error: {}", context, e))
+    }
+
+    pub fn with_hint(mut self, hint: &str) -> Self {
+        self.hint = Some(hint.to_string());
+        self
+    }
+
+    pub fn hint(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+
+    // Exit code of the program. Code 2 is used by argument parser for bad usage.
+    pub fn exit_code(&self) -> i32 {
+        match self.kind {
+            ErrorKind::General => 1,
+            ErrorKind::NotFound => 3,
+            ErrorKind::Conflict => 4,
+            ErrorKind::Io => 5,
+            ErrorKind::Parse => 6,
+            ErrorKind::Editor => 7,
+            ErrorKind::Hook => 8,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for AppError {}
+
+impl From<DatabaseError> for AppError {
+    fn from(e: DatabaseError) -> Self {
+        match e {
+            DatabaseError::NoteNotFound(_) => {
+                Self::not_found(&e.to_string()).with_hint("Use `kamiya list` to see all notes.")
+            }
+            DatabaseError::NoteExists(_) => Self::conflict(&e.to_string()),
+            DatabaseError::AmbiguousName(_, _) => {
+                Self::conflict(&e.to_string()).with_hint("Use the exact name of the note.")
+            }
+            DatabaseError::BadTemplate(_) => Self::parse(&e.to_string())
+                .with_hint("Fix it with `kamiya config set name_template Note&i`."),
+        }
+    }
+}
+
+impl From<ConfigError> for AppError {
+    fn from(e: ConfigError) -> Self {
+        Self::parse(&e.to_string()).with_hint("Use `kamiya config check` to find all problems.")
+    }
+}
+
+impl From<ProcessError> for AppError {
+    fn from(e: ProcessError) -> Self {
+        Self::editor(&e.to_string())
+    }
+}
+
+impl From<CryptoError> for AppError {
+    fn from(e: CryptoError) -> Self {
+        match e {
+            CryptoError::BadPassphrase | CryptoError::EncryptionFailed => {
+                Self::general(&e.to_string())
+            }
+            CryptoError::BadFormat => Self::parse(&e.to_string()),
+        }
+    }
+}
+
+impl From<BundleError> for AppError {
+    fn from(e: BundleError) -> Self {
+        match e {
+            BundleError::Io(_) => Self::new(ErrorKind::Io, &e.to_string()),
+            _ => Self::parse(&e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{error::AppError, manager::Manager};
+use kamiya_utils::proc::{self, ProcessError};
+use std::{fs, path::Path};
+
+// Backups are copies of database, they don't need history.
+const GITIGNORE: &str = "backups/\n";
+
+pub fn is_repo() -> bool {
+    Path::new(&Manager::get_data_dir()).join(".git").exists()
+}
+
+// Convert error of git into error of Kamiya.
+pub fn error(e: ProcessError) -> AppError {
+    match e {
+        ProcessError::ExecutableNotFound(_) => AppError::not_found("Git is not installed.")
+            .with_hint("Install git and make sure it's in your PATH."),
+        e => AppError::general(&format!("Git failed. {}", e)),
+    }
+}
+
+// Make data directory a git repository and commit current notes.
+pub fn init() -> Result<(), ProcessError> {
+    let dir: String = Manager::get_data_dir();
+    proc::run_git(&dir, &["init", "--quiet"])?;
+
+    let gitignore = Path::new(&dir).join(".gitignore");
+    if !gitignore.exists() {
+        fs::write(&gitignore, GITIGNORE).map_err(|e| ProcessError::Io(String::from("git"), e))?;
+    }
+    // Commits fail without author, so repository gets its own if user has none.
+    if proc::run_git(&dir, &["config", "user.email"]).is_err() {
+        proc::run_git(&dir, &["config", "user.name", "Kamiya"])?;
+        proc::run_git(&dir, &["config", "user.email", "kamiya@localhost"])?;
+    }
+    commit("Initial commit")
+}
+
+// Commit all changes in data directory. Does nothing if there are no changes.
+pub fn commit(message: &str) -> Result<(), ProcessError> {
+    let dir: String = Manager::get_data_dir();
+    proc::run_git(&dir, &["add", "--all"])?;
+    if proc::run_git(&dir, &["status", "--porcelain"])?
+        .trim()
+        .is_empty()
+    {
+        return Ok(());
+    }
+    proc::run_git(&dir, &["commit", "--quiet", "--message", message])?;
+    Ok(())
+}
+
+// Get history of data directory, from the newest commit.
+pub fn log(limit: Option<usize>, patch: bool, color: bool) -> Result<String, ProcessError> {
+    let mut args: Vec<String> = vec![
+        String::from("log"),
+        String::from("--date=format:%Y-%m-%d %H:%M"),
+        String::from("--format=%C(yellow)%h%C(reset)  %ad  %s"),
+        format!("--color={}", if color { "always" } else { "never" }),
+    ];
+    if let Some(limit) = limit {
+        args.push(format!("--max-count={}", limit));
+    }
+    if patch {
+        args.push(String::from("--patch"));
+    }
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    proc::run_git(&Manager::get_data_dir(), &args)
+}
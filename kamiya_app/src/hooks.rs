@@ -0,0 +1,39 @@
+use crate::{error::AppError, manager::Manager, term::Term};
+use kamiya_database::Note;
+use kamiya_utils::proc;
+use std::{env, time::Duration};
+
+// Name of the event, set for hook. Kamiya started by hook doesn't run hooks, so they can't loop.
+const EVENT_VARIABLE: &str = "KAMIYA_EVENT";
+
+// Run hook for event, like `post_take`, if it's set in configuration.
+// Failed pre-hook cancels operation if `hooks.abort_on_failure` is set, otherwise failure is only reported.
+pub fn run(event: &str, note: Option<&Note>) -> Result<(), AppError> {
+    if env::var_os(EVENT_VARIABLE).is_some() {
+        return Ok(());
+    }
+    let config = Manager::load_config_or_default();
+    let command: String = config.get_hook(event);
+    if command.trim().is_empty() {
+        return Ok(());
+    }
+
+    let mut vars: Vec<(&str, String)> = Manager::get_env_vars();
+    vars.push((EVENT_VARIABLE, event.to_string()));
+    vars.push(("KAMIYA_DATABASE_PATH", Manager::get_database_path()));
+    if let Some(note) = note {
+        vars.push(("KAMIYA_NOTE_NAME", note.name.clone()));
+        vars.push(("KAMIYA_NOTE_ID", note.id.clone()));
+    }
+
+    let timeout = Duration::from_secs(config.get_hook_timeout());
+    let Err(e) = proc::run_shell(&command, &vars, timeout) else {
+        return Ok(());
+    };
+    let message = format!("Hook `{}` failed. {}", event, e);
+    if event.starts_with("pre_") && config.get_abort_on_failure() {
+        return Err(AppError::hook(&message).with_hint("Operation has been cancelled."));
+    }
+    Term::warn(&message);
+    Ok(())
+}
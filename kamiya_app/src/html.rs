@@ -0,0 +1,214 @@
+use crate::markdown;
+use kamiya_database::Note;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+use serde_json::json;
+use std::{collections::HashSet, fs, io::Error, path::Path};
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:48em;margin:2em auto;padding:0 1em;line-height:1.5;color:#222}\
+a{color:#26a269}\
+pre{background:#f4f4f4;padding:1em;overflow:auto}\
+code{background:#f4f4f4}\
+input{width:100%;padding:.5em;font-size:1em;box-sizing:border-box}\
+.desc{color:#666}\
+.tag{color:#888;font-size:.9em;margin-right:.5em}";
+
+// Search index is loaded as script, because browsers don't let pages opened from disk fetch files.
+const SEARCH_SCRIPT: &str = r#"<script src="search.js"></script>
+<script>
+if (typeof NOTES !== "undefined") {
+  var input = document.getElementById("search");
+  input.hidden = false;
+  input.addEventListener("input", function () {
+    var query = input.value.toLowerCase();
+    NOTES.forEach(function (note) {
+      var text = [note.name, note.description, note.tags.join(" "), note.content].join(" ");
+      document.getElementById(note.id).hidden = text.toLowerCase().indexOf(query) === -1;
+    });
+  });
+}
+</script>"#;
+
+// Escape text to put it inside HTML.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Schemes which links and images can use. Destinations without scheme are relative.
+const URL_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+// Check if destination of link is safe to publish, so it can't be `javascript:` and such.
+fn is_safe_url(url: &str) -> bool {
+    // Browsers ignore whitespace and control characters in scheme.
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect();
+    match url.find([':', '/', '?', '#']) {
+        Some(index) if url[index..].starts_with(':') => URL_SCHEMES
+            .iter()
+            .any(|scheme| url[..index].eq_ignore_ascii_case(scheme)),
+        _ => true,
+    }
+}
+
+fn safe_url(url: CowStr) -> CowStr {
+    if is_safe_url(&url) {
+        url
+    } else {
+        CowStr::Borrowed("")
+    }
+}
+
+// Convert markdown to HTML. Raw HTML of notes is escaped and unsafe links are removed,
+// so published site can't run scripts from notes.
+pub fn render_markdown(content: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+
+    let mut output = String::new();
+    let parser = Parser::new_ext(content, options).map(|event| match event {
+        Event::Html(html) => Event::Text(html),
+        Event::Start(Tag::Link(kind, url, title)) => {
+            Event::Start(Tag::Link(kind, safe_url(url), title))
+        }
+        Event::Start(Tag::Image(kind, url, title)) => {
+            Event::Start(Tag::Image(kind, safe_url(url), title))
+        }
+        event => event,
+    });
+    html::push_html(&mut output, parser);
+    output
+}
+
+fn render_page(title: &str, stylesheet: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<link rel=\"stylesheet\" href=\"{}\">\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        stylesheet,
+        body
+    )
+}
+
+fn render_tags(tags: &[String]) -> String {
+    tags.iter()
+        .map(|tag| format!("<span class=\"tag\">#{}</span>", escape(tag)))
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+// Write notes as static site with index page, page for every note and search index.
+pub fn write_site(path: &Path, notes: &[Note]) -> Result<(), Error> {
+    fs::create_dir_all(path.join("notes"))?;
+    fs::write(path.join("style.css"), STYLE)?;
+
+    let mut taken: HashSet<String> = HashSet::new();
+    let mut index = String::from("<h1>Notes</h1>\n<input id=\"search\" type=\"search\" placeholder=\"Search...\" hidden>\n<ul>\n");
+    let mut search = Vec::new();
+
+    for note in notes {
+        let slug = markdown::unique_slug(&note.name, &mut taken);
+        let url = format!("notes/{}.html", slug);
+
+        let mut body = format!(
+            "<p><a href=\"../index.html\">All notes</a></p>\n<h1>{}</h1>\n",
+            escape(&note.name)
+        );
+        if !note.description.is_empty() {
+            body += &format!("<p class=\"desc\">{}</p>\n", escape(&note.description));
+        }
+        if !note.tags.is_empty() {
+            body += &format!("<p>{}</p>\n", render_tags(&note.tags));
+        }
+        // Content of secret notes is encrypted, so it can't be published.
+        if note.secret {
+            body += "<p><em>This note is secret.</em></p>\n";
+        } else {
+            body += &render_markdown(&note.content);
+        }
+        fs::write(
+            path.join(&url),
+            render_page(&note.name, "../style.css", &body),
+        )?;
+
+        index += &format!(
+            "<li id=\"{}\"><a href=\"{}\">{}</a> <span class=\"desc\">{}</span> {}</li>\n",
+            slug,
+            url,
+            escape(&note.name),
+            escape(&note.description),
+            render_tags(&note.tags)
+        );
+        search.push(json!({
+            "id": slug,
+            "name": note.name,
+            "description": note.description,
+            "tags": note.tags,
+            "url": url,
+            "content": if note.secret { "" } else { &note.content },
+        }));
+    }
+
+    index += "</ul>\n";
+    index += SEARCH_SCRIPT;
+    index += "\n";
+    fs::write(
+        path.join("index.html"),
+        render_page("Notes", "style.css", &index),
+    )?;
+    let search = serde_json::to_string(&search).map_err(Error::other)?;
+    fs::write(path.join("search.js"), format!("var NOTES = {};\n", search))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_html_is_escaped() {
+        let output = render_markdown("<script>alert(1)</script>\n\ntext <b>bold</b>");
+        assert!(!output.contains("<script>"));
+        assert!(!output.contains("<b>"));
+        assert!(output.contains("&lt;script&gt;"));
+    }
+
+    #[test]
+    fn safe_links_are_kept() {
+        let output = render_markdown(
+            "[a](https://example.com) [b](http://example.com) [c](mailto:me@example.com) \
+             [d](other.html) [e](/notes/x.html#top) [f](?q=a:b) ![g](images/g.png)",
+        );
+        assert!(output.contains("href=\"https://example.com\""));
+        assert!(output.contains("href=\"http://example.com\""));
+        assert!(output.contains("href=\"mailto:me@example.com\""));
+        assert!(output.contains("href=\"other.html\""));
+        assert!(output.contains("href=\"/notes/x.html#top\""));
+        assert!(output.contains("href=\"?q=a:b\""));
+        assert!(output.contains("src=\"images/g.png\""));
+    }
+
+    #[test]
+    fn unsafe_links_are_removed() {
+        let output = render_markdown(
+            "[a](javascript:alert(1)) [b](JavaScript:alert(1)) [c](<java\tscript:alert(1)>) \
+             [d](java&#115;cript:alert(1)) <javascript:alert(1)> ![e](data:image/svg+xml,x) \
+             [f]\n\n[f]: vbscript:x",
+        );
+        assert_eq!(output.matches("href=").count(), 6);
+        assert_eq!(output.matches("href=\"\"").count(), 6);
+        assert!(output.contains("src=\"\""));
+    }
+}
@@ -1,28 +1,138 @@
 use crate::args::args;
-use crate::term::Term;
+use crate::term::{Output, Term};
 use actions::Actions;
+use clap::ArgMatches;
+use clap_complete::Shell;
+use error::AppError;
+use kamiya_database::OnConflict;
 use manager::Manager;
-use std::{fs, path::Path, process::exit};
+use std::{env, ffi::OsString, fs, path::Path, process::exit};
 
 mod actions;
+mod alias;
 mod args;
+mod bundle;
+mod completions;
+mod doctor;
+mod error;
+mod git;
+mod hooks;
+mod html;
+mod man;
 mod manager;
+mod markdown;
+mod obsidian;
+mod plugin;
 mod term;
 
 fn main() {
+    let args = parse_args().unwrap_or_else(|e| fail(e));
+    if let Err(e) = run(&args) {
+        fail(e);
+    }
+}
+
+fn fail(e: AppError) -> ! {
+    Term::fatal(&e.to_string());
+    if let Some(hint) = e.hint() {
+        Term::hint(hint);
+    }
+    exit(e.exit_code());
+}
+
+// Parse arguments after aliases from configuration are expanded.
+fn parse_args() -> Result<ArgMatches, AppError> {
+    let raw: Vec<OsString> = env::args_os().collect();
+    // Configuration files depend on global arguments, so they are applied first.
+    if let Ok(matches) = args().ignore_errors(true).try_get_matches_from(&raw) {
+        apply_globals(&matches);
+    }
+    let aliases = Manager::load_aliases();
+    let raw: Vec<OsString> = alias::expand(&mut args(), raw, &aliases)?;
+
+    let mut command = args();
+    if !aliases.is_empty() {
+        command = command.after_help(alias::help(&aliases));
+    }
+    Ok(command.get_matches_from(raw))
+}
+
+// Apply arguments which choose files and options for the whole run.
+fn apply_globals(args: &ArgMatches) {
+    if let Some(path) = args.get_one::<String>("data_dir") {
+        Manager::set_data_dir(path);
+    }
+    if let Some(profile) = args.get_one::<String>("profile") {
+        Manager::set_profile(profile);
+    }
+    if let Some(overrides) = args.get_many::<String>("set") {
+        Manager::set_overrides(overrides.cloned().collect());
+    }
+}
+
+// Get strategy for notes with the same name. `None` means such notes are skipped.
+fn get_on_conflict(args: &ArgMatches) -> Option<OnConflict> {
+    match args.get_one::<String>("on_conflict").map(String::as_str) {
+        Some("fail") => Some(OnConflict::Fail),
+        Some("overwrite") => Some(OnConflict::Overwrite),
+        Some("suffix") => Some(OnConflict::Suffix),
+        _ => None,
+    }
+}
+
+fn run(args: &ArgMatches) -> Result<(), AppError> {
+    // Output of these commands is read by programs, so messages must not mix with it.
+    match args.subcommand_name() {
+        Some("plugin-api") => Term::set_output(Output::Stderr),
+        // Shell shows everything printed by completion as candidates.
+        Some("__complete-names") => Term::set_output(Output::Silent),
+        _ => {}
+    }
+    // Completion script and manual pages don't need database.
+    match args.subcommand() {
+        Some(("completions", _sub)) => {
+            Actions::completions(*_sub.get_one::<Shell>("shell").unwrap());
+            return Ok(());
+        }
+        Some(("man", _sub)) => {
+            return Actions::man(_sub.get_one::<String>("out").map(String::as_str));
+        }
+        _ => {}
+    }
+
+    apply_globals(args);
+
+    Manager::check_home()?;
+    Manager::migrate()?;
+    Manager::validate_profile_name(&Manager::get_profile())?;
+    // Profile commands must work even if active profile is missing.
+    let is_profile_command = matches!(args.subcommand(), Some(("profile", _)));
+    if !is_profile_command && !Manager::profile_exists(&Manager::get_profile()) {
+        return Err(AppError::not_found(&format!(
+            "Profile '{}' not found!",
+            Manager::get_profile()
+        ))
+        .with_hint("Use `kamiya profile list` to see available profiles."));
+    }
     if !Manager::check_db() || !Manager::check_config() {
-        Manager::make_default();
+        Manager::make_default()?;
     }
 
-    if Path::new(&Manager::get_old_config_path()).exists() {
+    if let Some(old_path) = Manager::get_old_config_path().filter(|p| Path::new(p).exists()) {
         Term::warn("In Kamiya 0.6.0 database structure has been changed. You old database has been saved as `kamiya.yaml.bak`.");
         Term::warn("You should import all your old notes manually. Sorry for this!");
-        let old_database = fs::read_to_string(Manager::get_old_config_path()).unwrap();
-        fs::write(Manager::get_old_config_path() + ".bak", old_database).unwrap();
-        fs::remove_file(Manager::get_old_config_path()).unwrap();
+        let old_database = fs::read_to_string(&old_path)
+            .map_err(|e| AppError::io("Failed to read old database.", e))?;
+        fs::write(old_path.clone() + ".bak", old_database)
+            .map_err(|e| AppError::io("Failed to save old database.", e))?;
+        fs::remove_file(&old_path)
+            .map_err(|e| AppError::io("Failed to remove old database.", e))?;
     }
 
-    let args = args().get_matches();
+    // Actions which change notes replace it with more specific description.
+    if let Some(name) = args.subcommand_name() {
+        Manager::set_change(name);
+    }
     match args.subcommand() {
         Some(("take", _sub)) => {
             let content: &str = _sub.get_one::<String>("content").unwrap();
@@ -30,22 +140,23 @@ fn main() {
             let desc: &str = _sub.get_one::<String>("description").unwrap();
 
             if content.is_empty() {
-                Term::fatal("You cant take a note with empty content.");
-                exit(1);
+                return Err(AppError::general(
+                    "You cant take a note with empty content.",
+                ));
             }
 
-            Actions::take(content, &mut name, desc);
+            Actions::take(content, &mut name, desc, _sub.get_flag("secret"))?;
         }
         Some(("add", _sub)) => {
             let filename: &str = _sub.get_one::<String>("filename").unwrap();
             let mut name: String = _sub.get_one::<String>("name").unwrap().to_string();
 
             if filename.is_empty() {
-                Term::fatal("You give no path to file.");
-                exit(1);
+                return Err(AppError::general("You give no path to file."));
             }
 
-            Actions::add(filename, &mut name);
+            let on_conflict: OnConflict = get_on_conflict(_sub).unwrap_or(OnConflict::Fail);
+            Actions::add(filename, &mut name, on_conflict)?;
         }
         Some(("desc", _sub)) => {
             let name: &str = _sub.get_one::<String>("name").unwrap();
@@ -53,108 +164,210 @@ fn main() {
             let desc: &str = _sub.get_one::<String>("desc").unwrap();
 
             if name.is_empty() {
-                Term::fatal("Cannot set description for the void.");
-                exit(1);
+                return Err(AppError::general("Cannot set description for the void."));
             }
 
-            Actions::desc(name, desc);
+            Actions::desc(name, desc)?;
         }
         Some(("rename", _sub)) => {
             let old_name: &str = _sub.get_one::<String>("old_name").unwrap().as_str();
             let new_name: &str = _sub.get_one::<String>("new_name").unwrap().as_str();
-            Actions::rename(old_name, new_name);
+            let on_conflict: OnConflict = get_on_conflict(_sub).unwrap_or(OnConflict::Fail);
+            Actions::rename(old_name, new_name, on_conflict)?;
         }
         Some(("get", _sub)) => {
             let name: &str = _sub.get_one::<String>("name").unwrap();
 
             if name.is_empty() {
-                Term::fatal("You didn't give a name for the note.");
-                exit(1);
+                return Err(AppError::general("You didn't give a name for the note."));
             }
 
-            Actions::get(name);
+            Actions::get(name, _sub.get_flag("no_pager"))?;
         }
         Some(("open", _sub)) => {
             let name: &str = _sub.get_one::<String>("name").unwrap();
 
             if name.is_empty() {
-                Term::fatal("You didn't give a name for the note.");
-                exit(1);
+                return Err(AppError::general("You didn't give a name for the note."));
             }
 
-            Actions::open(name);
-        }
-        Some(("editor", _sub)) => {
-            let editor: &str = _sub
-                .get_one::<String>("editor")
-                .expect("Cannot read argument content.")
-                .as_str();
-
-            Actions::editor(editor);
-        }
-        Some(("template", _sub)) => {
-            let template: &str = _sub.get_one::<String>("template").unwrap();
-
-            Actions::template(template);
+            Actions::open(name, *_sub.get_one::<usize>("line").unwrap())?;
         }
         Some(("delete", _sub)) => {
             let name: &str = _sub.get_one::<String>("name").unwrap();
 
             if name.is_empty() {
-                Term::fatal("You didn't pass a name to search for.");
-                exit(1);
+                return Err(AppError::general("You didn't pass a name to search for."));
             }
 
-            Actions::delete(name);
+            Actions::delete(name)?;
         }
         Some(("search", _sub)) => {
             let pattern: &str = _sub.get_one::<String>("pattern").unwrap();
 
-            Actions::search(pattern);
+            Actions::search(pattern, _sub.get_flag("no_pager"))?;
         }
         Some(("list", _sub)) => {
-            Actions::list();
+            Actions::list(_sub.get_flag("no_pager"))?;
         }
         Some(("save", _sub)) => {
             let name: &str = _sub.get_one::<String>("name").unwrap();
             let mut filename: String = _sub.get_one::<String>("filename").unwrap().to_string();
 
             if name.is_empty() {
-                Term::fatal("You didn't give a name for the note.");
-                exit(1);
+                return Err(AppError::general("You didn't give a name for the note."));
             }
 
             if filename.is_empty() {
-                Term::fatal("Bad name for file!");
-                exit(1);
+                return Err(AppError::general("Bad name for file!"));
             }
 
             if Path::new(&filename).exists() {
-                Term::fatal("Same file already exists in file system.");
-                exit(1);
+                return Err(AppError::conflict(
+                    "Same file already exists in file system.",
+                ));
             }
 
-            Actions::save(name, &mut filename);
+            Actions::save(name, &mut filename)?;
         }
         Some(("export", _sub)) => {
-            let path: &str = _sub.get_one::<String>("path").unwrap();
+            let format: &str = _sub.get_one::<String>("format").unwrap();
+            let path: &str = match _sub.get_one::<String>("path") {
+                Some(path) => path,
+                None if format == "markdown" => "kamiya_exported",
+                None if format == "html" => "kamiya_site",
+                None => "kamiya_exported.json",
+            };
 
-            Actions::export(path);
+            match _sub.get_one::<String>("bundle") {
+                Some(bundle) => Actions::export_bundle(bundle)?,
+                None => Actions::export(path, format)?,
+            }
         }
         Some(("import", _sub)) => {
             let filename: &str = _sub.get_one::<String>("filename").unwrap();
+            let from_dir: Option<&String> = _sub.get_one::<String>("from_dir");
+            let vault: Option<&String> = _sub.get_one::<String>("obsidian");
+            let bundle: Option<&String> = _sub.get_one::<String>("bundle");
+            let glob: &str = _sub.get_one::<String>("glob").unwrap();
             let replace: bool = _sub.get_flag("replace");
             let interactive: bool = _sub.get_flag("interactive");
 
             if interactive && replace {
-                Term::fatal("`interactive` and `replace` cant be set at the same time.");
-                exit(1);
+                return Err(AppError::general(
+                    "`interactive` and `replace` cant be set at the same time.",
+                ));
+            }
+
+            let on_conflict: Option<OnConflict> = match replace {
+                true => Some(OnConflict::Overwrite),
+                false => get_on_conflict(_sub),
+            };
+
+            match (from_dir, vault, bundle) {
+                (Some(path), _, _) => Actions::import_dir(path, glob, on_conflict, interactive)?,
+                (_, Some(path), _) => Actions::import_obsidian(path, on_conflict, interactive)?,
+                (_, _, Some(path)) => Actions::import_bundle(path, on_conflict, interactive)?,
+                _ => Actions::import(filename, on_conflict, interactive)?,
             }
+        }
+        Some(("lock", _sub)) => {
+            let name: &str = _sub.get_one::<String>("name").unwrap();
 
-            Actions::import(filename, replace, interactive);
+            Actions::lock(name)?;
+        }
+        Some(("unlock", _sub)) => {
+            let name: &str = _sub.get_one::<String>("name").unwrap();
+
+            Actions::unlock(name)?;
+        }
+        Some(("profile", _sub)) => match _sub.subcommand() {
+            Some(("create", _cmd)) => {
+                Actions::profile_create(_cmd.get_one::<String>("name").unwrap())?;
+            }
+            Some(("list", _cmd)) => {
+                Actions::profile_list();
+            }
+            Some(("use", _cmd)) => {
+                Actions::profile_use(_cmd.get_one::<String>("name").unwrap())?;
+            }
+            Some(("delete", _cmd)) => {
+                let name: &str = _cmd.get_one::<String>("name").unwrap();
+                Actions::profile_delete(name, _cmd.get_flag("yes"))?;
+            }
+            _ => return Err(AppError::general("Unknown profile command!")),
+        },
+        Some(("config", _sub)) => match _sub.subcommand() {
+            Some(("get", _cmd)) => {
+                Actions::config_get(_cmd.get_one::<String>("key").unwrap())?;
+            }
+            Some(("set", _cmd)) => {
+                let key: &str = _cmd.get_one::<String>("key").unwrap();
+                let value: &str = _cmd.get_one::<String>("value").unwrap();
+                Actions::config_set(key, value)?;
+            }
+            Some(("list", _cmd)) => {
+                Actions::config_list()?;
+            }
+            Some(("edit", _cmd)) => {
+                Actions::config_edit()?;
+            }
+            Some(("check", _cmd)) => {
+                Actions::config_check()?;
+            }
+            Some(("path", _cmd)) => {
+                Actions::config_path(_cmd.get_flag("all"));
+            }
+            _ => return Err(AppError::general("Unknown config command!")),
+        },
+        Some(("__complete-names", _sub)) => {
+            let words: Vec<String> = _sub
+                .get_many::<String>("words")
+                .unwrap_or_default()
+                .cloned()
+                .collect();
+            if !Actions::complete_names(&words) {
+                exit(1);
+            }
+        }
+        Some(("plugin-api", _sub)) => {
+            Actions::plugin_api()?;
+        }
+        Some(("doctor", _sub)) => {
+            Actions::doctor(_sub.get_flag("fix"))?;
+        }
+        Some(("encrypt", _sub)) => {
+            Actions::encrypt(_sub.get_flag("yes"))?;
+        }
+        Some(("decrypt", _sub)) => {
+            Actions::decrypt()?;
+        }
+        Some(("git", _sub)) => match _sub.subcommand() {
+            Some(("init", _cmd)) => {
+                Actions::git_init()?;
+            }
+            _ => return Err(AppError::general("Unknown git command!")),
+        },
+        Some(("log", _sub)) => {
+            Actions::log(
+                _sub.get_one::<usize>("limit").copied(),
+                _sub.get_flag("patch"),
+                _sub.get_flag("no_pager"),
+            )?;
+        }
+        Some((name, _sub)) => {
+            let plugin_args: Vec<OsString> = _sub
+                .get_many::<OsString>("")
+                .unwrap_or_default()
+                .cloned()
+                .collect();
+            exit(Actions::run_plugin(name, &plugin_args)?);
+        }
+        _ => {
+            return Err(AppError::general(
+                "Unknown command! Use argument '--help' to get full list of available commands.",
+            ))
         }
-        _ => Term::fatal(
-            "Unknown command! Use argument '--help' to get full list of available commands.",
-        ),
     }
+    Ok(())
 }
@@ -0,0 +1,201 @@
+use clap::Command;
+use clap_mangen::Man;
+use roff::{bold, italic, roman, Inline, Roff};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+// Options of configuration file, with their descriptions.
+const OPTIONS: [(&str, &str); 4] = [
+    (
+        "name_template",
+        "Template for names of new notes. Must contain &i, which is replaced with number of the note. Default is Note&i.",
+    ),
+    (
+        "editor",
+        "Command to edit notes with, like code --wait. {file} and {line} are replaced with path to the note and line number, otherwise path is added to the end. If empty, $VISUAL or $EDITOR is used. Default is empty.",
+    ),
+    (
+        "pager",
+        "Pager for output that doesn't fit on the screen. If empty, $PAGER is used, or less if it's not set.",
+    ),
+    (
+        "ignore_case",
+        "Find notes by name ignoring case and Unicode normal form. Default is false.",
+    ),
+];
+
+const ENVIRONMENT: [(&str, &str); 15] = [
+    (
+        "KAMIYA_HOME",
+        "Directory to store configuration and database in. Same as --data-dir.",
+    ),
+    (
+        "KAMIYA_PROFILE",
+        "Profile to use instead of the active one. Same as --profile.",
+    ),
+    ("KAMIYA_PASSPHRASE", "Passphrase for encrypted database."),
+    ("KAMIYA_NOTE_PASSPHRASE", "Passphrase for secret notes."),
+    ("KAMIYA_NAME_TEMPLATE", "Overrides name_template option."),
+    ("KAMIYA_EDITOR", "Overrides editor option."),
+    ("KAMIYA_PAGER", "Overrides pager option."),
+    ("KAMIYA_IGNORE_CASE", "Overrides ignore_case option."),
+    (
+        "KAMIYA_EDITORS_<EXT>",
+        "Overrides editor for notes with extension, like KAMIYA_EDITORS_MD for editors.md.",
+    ),
+    (
+        "KAMIYA_HOOKS_<NAME>",
+        "Overrides option in hooks table, like KAMIYA_HOOKS_TIMEOUT for hooks.timeout.",
+    ),
+    (
+        "KAMIYA_DATA_DIR, KAMIYA_CONFIG_PATH, KAMIYA_EXE",
+        "Set by Kamiya for plugins. Plugins are run for unknown commands, like kamiya-foo for kamiya foo.",
+    ),
+    (
+        "KAMIYA_EVENT, KAMIYA_NOTE_NAME, KAMIYA_NOTE_ID, KAMIYA_DATABASE_PATH",
+        "Set by Kamiya for hooks. Kamiya started with KAMIYA_EVENT doesn't run hooks.",
+    ),
+    ("VISUAL, EDITOR", "Editor to use if editor option is empty."),
+    ("PAGER", "Pager to use if pager option is empty."),
+    (
+        "XDG_CONFIG_HOME, XDG_DATA_HOME",
+        "Base directories for configuration and database.",
+    ),
+];
+
+const FILES: [(&str, &str); 5] = [
+    (
+        "/etc/kamiya/config.toml",
+        "System configuration, shared by all users.",
+    ),
+    ("~/.config/kamiya/config.toml", "User configuration."),
+    (
+        "~/.local/share/kamiya/database.json",
+        "Database with notes.",
+    ),
+    (
+        "~/.local/share/kamiya/profiles/<name>/",
+        "Database and configuration overrides of profile.",
+    ),
+    (
+        "~/.local/share/kamiya/.git/",
+        "History of notes, created with kamiya git init.",
+    ),
+];
+
+const EXIT_STATUS: [(&str, &str); 9] = [
+    ("0", "Success."),
+    ("1", "General error, like wrong passphrase."),
+    ("2", "Bad usage of command line arguments."),
+    ("3", "Note, profile, option or file not found."),
+    ("4", "Conflict, like note or file that already exists."),
+    ("5", "Failed to read or write file."),
+    (
+        "6",
+        "Failed to parse configuration, database or imported data.",
+    ),
+    ("7", "Editor failed or wasn't found."),
+    ("8", "Hook failed and operation has been cancelled."),
+];
+
+// Add list of terms with descriptions to the page.
+fn render_list(roff: &mut Roff, items: &[(&str, &str)]) {
+    for (term, description) in items {
+        roff.control("TP", [])
+            .text([bold(*term)])
+            .text([roman(*description)]);
+    }
+}
+
+// Sections of main page which can't be generated from arguments.
+fn render_extra(roff: &mut Roff) {
+    roff.control("SH", ["CONFIGURATION"]);
+    roff.text([
+        roman("Configuration is a TOML file. Options are read from "),
+        italic("[options]"),
+        roman(" table:"),
+    ]);
+    render_list(roff, &OPTIONS);
+    roff.control("PP", []).text([
+        roman("Editors for notes with specific extensions are set in "),
+        italic("[editors]"),
+        roman(" table, like md = \"nvim {file}\"."),
+    ]);
+    roff.control("PP", []).text([
+        roman("Shell commands run on events are set in "),
+        italic("[hooks]"),
+        roman(
+            " table: post_take, post_edit, post_delete, pre_write_database and post_write_database. \
+             Hook is killed with its children after timeout seconds. Failed pre-hook cancels operation, unless abort_on_failure is false.",
+        ),
+    ]);
+    roff.control("PP", []).text([roman(
+        "Options are read from built-in defaults, system configuration, user configuration, \
+         configuration of the active profile, KAMIYA_* environment variables and --set argument. \
+         Every next one overrides the previous.",
+    )]);
+
+    roff.control("SH", ["ENVIRONMENT"]);
+    render_list(roff, &ENVIRONMENT);
+    roff.control("SH", ["FILES"]);
+    render_list(roff, &FILES);
+    roff.control("SH", ["EXIT STATUS"]);
+    render_list(roff, &EXIT_STATUS);
+    roff.control("SH", ["SEE ALSO"]);
+    let mut see_also: Vec<Inline> = Vec::new();
+    for (index, name) in ["kamiya-take", "kamiya-open", "kamiya-config"]
+        .iter()
+        .enumerate()
+    {
+        if index > 0 {
+            see_also.push(roman(", "));
+        }
+        see_also.push(bold(*name));
+        see_also.push(roman("(1)"));
+    }
+    roff.text(see_also);
+}
+
+// Render main page with description of configuration, environment, files and exit codes.
+pub fn render(cmd: Command, w: &mut dyn Write) -> io::Result<()> {
+    let man = Man::new(cmd);
+    man.render_title(w)?;
+    man.render_name_section(w)?;
+    man.render_synopsis_section(w)?;
+    man.render_description_section(w)?;
+    man.render_options_section(w)?;
+    man.render_subcommands_section(w)?;
+    let mut roff = Roff::new();
+    render_extra(&mut roff);
+    roff.to_writer(w)?;
+    man.render_version_section(w)?;
+    man.render_authors_section(w)
+}
+
+// Write pages of Kamiya and every subcommand to directory. Returns paths to written pages.
+pub fn generate_to(cmd: Command, dir: &Path) -> io::Result<Vec<PathBuf>> {
+    fn generate_subcommands(cmd: &Command, dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+        for subcommand in cmd.get_subcommands().filter(|s| !s.is_hide_set()) {
+            generate_subcommands(subcommand, dir, paths)?;
+            let source = format!("kamiya {}", env!("CARGO_PKG_VERSION"));
+            let man = Man::new(subcommand.clone()).source(source);
+            paths.push(man.generate_to(dir)?);
+        }
+        Ok(())
+    }
+
+    let mut cmd = cmd.disable_help_subcommand(true);
+    cmd.build();
+    fs::create_dir_all(dir)?;
+    let mut paths: Vec<PathBuf> = Vec::new();
+    generate_subcommands(&cmd, dir, &mut paths)?;
+
+    let path = dir.join("kamiya.1");
+    let mut file = fs::File::create(&path)?;
+    render(cmd, &mut file)?;
+    paths.insert(0, path);
+    Ok(paths)
+}
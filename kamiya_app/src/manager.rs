@@ -1,53 +1,556 @@
+use crate::{error::AppError, git, hooks, term::Term};
 use home::home_dir;
-use kamiya_config::Config;
+use kamiya_config::{self as config, Config, ConfigError};
 use kamiya_database::Database;
-use std::{fs, path::Path};
+use kamiya_utils::crypto::{self, CryptoError};
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+
+// Passphrase of encrypted database. If set, database will be encrypted on write.
+static PASSPHRASE: Mutex<Option<String>> = Mutex::new(None);
+
+// Directory set with `--data-dir`. Has priority over `KAMIYA_HOME`.
+static DATA_DIR: Mutex<Option<String>> = Mutex::new(None);
+
+// Profile set with `--profile`. Has priority over `KAMIYA_PROFILE` and profile chosen with `profile use`.
+static PROFILE: Mutex<Option<String>> = Mutex::new(None);
+
+// Configuration built from all layers.
+static CONFIG: Mutex<Option<toml::Value>> = Mutex::new(None);
+
+// Options set with `--set key=value`. Have priority over everything else.
+static OVERRIDES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+// Description of the current change, like `take: Note5`. Used as message of git commit.
+static CHANGE: Mutex<String> = Mutex::new(String::new());
+
+pub const DEFAULT_PROFILE: &str = "default";
 
 pub struct Manager;
 impl Manager {
+    fn get_home() -> Option<String> {
+        home_dir().map(|path| path.display().to_string())
+    }
+
+    // Directories of Kamiya are in home directory, unless user has chosen another one.
+    pub fn check_home() -> Result<(), AppError> {
+        if Self::get_custom_dir().is_some() || Self::get_home().is_some() {
+            return Ok(());
+        }
+        Err(AppError::not_found("Cannot find your home directory.")
+            .with_hint("Set `KAMIYA_HOME` to choose where to store notes."))
+    }
+
+    // Get directory from XDG variable. Relative paths are ignored, as specification says.
+    // Home directory must be checked with `check_home` first.
+    fn get_xdg_dir(variable: &str, default: &str) -> String {
+        match env::var(variable) {
+            Ok(path) if Path::new(&path).is_absolute() => path,
+            _ => Path::new(&Self::get_home().unwrap_or_default())
+                .join(default)
+                .display()
+                .to_string(),
+        }
+    }
+
+    pub fn set_data_dir(path: &str) {
+        *DATA_DIR.lock().unwrap() = Some(path.to_string());
+    }
+
+    // Directory which is set by user to store both configuration and database.
+    pub fn get_custom_dir() -> Option<String> {
+        if let Some(path) = DATA_DIR.lock().unwrap().as_ref() {
+            return Some(path.clone());
+        }
+        match env::var("KAMIYA_HOME") {
+            Ok(path) if !path.is_empty() => Some(path),
+            _ => None,
+        }
+    }
+
+    pub fn get_config_dir() -> String {
+        match Self::get_custom_dir() {
+            Some(path) => path,
+            None => Path::new(&Self::get_xdg_dir("XDG_CONFIG_HOME", ".config"))
+                .join("kamiya")
+                .display()
+                .to_string(),
+        }
+    }
+
+    pub fn get_data_dir() -> String {
+        match Self::get_custom_dir() {
+            Some(path) => path,
+            None => Path::new(&Self::get_xdg_dir("XDG_DATA_HOME", ".local/share"))
+                .join("kamiya")
+                .display()
+                .to_string(),
+        }
+    }
+
+    // Environment variables for plugins and hooks, so Kamiya started by them uses the same files.
+    pub fn get_env_vars() -> Vec<(&'static str, String)> {
+        let mut vars: Vec<(&str, String)> = vec![
+            ("KAMIYA_DATA_DIR", Self::get_data_dir()),
+            ("KAMIYA_CONFIG_PATH", Self::get_config_path()),
+            ("KAMIYA_PROFILE", Self::get_profile()),
+        ];
+        if let Some(dir) = Self::get_custom_dir() {
+            vars.push(("KAMIYA_HOME", dir));
+        }
+        if let Ok(exe) = env::current_exe() {
+            vars.push(("KAMIYA_EXE", exe.display().to_string()));
+        }
+        vars
+    }
+
     pub fn get_config_path() -> String {
-        home_dir().unwrap().display().to_string() + "/.config/kamiya/config.toml"
+        Path::new(&Self::get_config_dir())
+            .join("config.toml")
+            .display()
+            .to_string()
     }
 
-    pub fn get_old_config_path() -> String {
-        home_dir().unwrap().display().to_string() + "/.config/kamiya.yaml"
+    pub fn get_old_config_path() -> Option<String> {
+        Self::get_home().map(|home| home + "/.config/kamiya.yaml")
     }
 
     pub fn get_database_path() -> String {
-        home_dir().unwrap().display().to_string() + "/.config/kamiya/database.json"
+        Self::get_profile_database_path(&Self::get_profile())
     }
 
-    pub fn get_config_dir() -> String {
-        home_dir().unwrap().display().to_string() + "/.config/kamiya"
+    pub fn set_profile(name: &str) {
+        *PROFILE.lock().unwrap() = Some(name.to_string());
+    }
+
+    // Get name of the active profile.
+    pub fn get_profile() -> String {
+        if let Some(name) = PROFILE.lock().unwrap().as_ref() {
+            return name.clone();
+        }
+        if let Ok(name) = env::var("KAMIYA_PROFILE") {
+            if !name.is_empty() {
+                return name;
+            }
+        }
+        match fs::read_to_string(Self::get_active_profile_path()) {
+            Ok(name) if !name.trim().is_empty() => name.trim().to_string(),
+            _ => String::from(DEFAULT_PROFILE),
+        }
+    }
+
+    // Profile name becomes part of path, so it can't contain separators or `..`.
+    pub fn validate_profile_name(name: &str) -> Result<(), AppError> {
+        if !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            return Ok(());
+        }
+        Err(
+            AppError::general(&format!("Profile name '{}' is not valid.", name))
+                .with_hint("Profile name can contain only letters, digits, `-` and `_`."),
+        )
+    }
+
+    // File which stores name of the profile chosen with `profile use`.
+    pub fn get_active_profile_path() -> String {
+        Path::new(&Self::get_data_dir())
+            .join("profile")
+            .display()
+            .to_string()
+    }
+
+    pub fn get_profiles_dir() -> String {
+        Path::new(&Self::get_data_dir())
+            .join("profiles")
+            .display()
+            .to_string()
+    }
+
+    pub fn get_profile_dir(name: &str) -> String {
+        Path::new(&Self::get_profiles_dir())
+            .join(name)
+            .display()
+            .to_string()
+    }
+
+    pub fn get_profile_database_path(name: &str) -> String {
+        if name == DEFAULT_PROFILE {
+            return Path::new(&Self::get_data_dir())
+                .join("database.json")
+                .display()
+                .to_string();
+        }
+        Path::new(&Self::get_profile_dir(name))
+            .join("database.json")
+            .display()
+            .to_string()
+    }
+
+    // Configuration file with options that override user configuration for the profile.
+    pub fn get_profile_config_path(name: &str) -> String {
+        Path::new(&Self::get_profile_dir(name))
+            .join("config.toml")
+            .display()
+            .to_string()
+    }
+
+    pub fn get_backups_dir() -> String {
+        Path::new(&Self::get_data_dir())
+            .join("backups")
+            .display()
+            .to_string()
+    }
+
+    // Copy database and configuration of the active profile to new backup directory.
+    // Returns path to this directory.
+    pub fn backup() -> Result<String, AppError> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let mut dir = Path::new(&Self::get_backups_dir()).join(timestamp.to_string());
+        let mut counter = 2;
+        while dir.exists() {
+            dir = Path::new(&Self::get_backups_dir()).join(format!("{}-{}", timestamp, counter));
+            counter += 1;
+        }
+        fs::create_dir_all(&dir)
+            .map_err(|e| AppError::io("Failed to create backup directory.", e))?;
+
+        let files = [
+            (Self::get_database_path(), "database.json"),
+            (Self::get_config_path(), "config.toml"),
+            (
+                Self::get_profile_config_path(&Self::get_profile()),
+                "profile-config.toml",
+            ),
+        ];
+        for (path, name) in files {
+            if Path::new(&path).exists() {
+                fs::read(&path)
+                    .and_then(|content| Self::write_private(&dir.join(name), &content))
+                    .map_err(|e| AppError::io(&format!("Failed to back up '{}'.", path), e))?;
+            }
+        }
+        Ok(dir.display().to_string())
     }
 
-    pub fn load_config() -> Config {
-        let content =
-            fs::read_to_string(Self::get_config_path()).expect("Unable to read the file.");
-        match toml::from_str(&content) {
-            Ok(cfg) => cfg,
-            Err(_) => panic!("Failed to parse configuration file."),
+    pub fn profile_exists(name: &str) -> bool {
+        name == DEFAULT_PROFILE || Path::new(&Self::get_profile_dir(name)).is_dir()
+    }
+
+    // Get names of all profiles, including default one.
+    pub fn get_profiles() -> Vec<String> {
+        let mut profiles: Vec<String> = match fs::read_dir(Self::get_profiles_dir()) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        profiles.sort();
+        profiles.insert(0, String::from(DEFAULT_PROFILE));
+        profiles
+    }
+
+    // Move files from `~/.config/kamiya`, where Kamiya 0.6.0 kept them, to XDG directories.
+    pub fn migrate() -> Result<(), AppError> {
+        if Self::get_custom_dir().is_some() {
+            return Ok(());
+        }
+
+        let Some(home) = Self::get_home() else {
+            return Ok(());
+        };
+        let legacy_dir = Path::new(&home).join(".config/kamiya");
+        let moves = [
+            (
+                legacy_dir.join("database.json"),
+                Self::get_profile_database_path(DEFAULT_PROFILE),
+            ),
+            (legacy_dir.join("config.toml"), Self::get_config_path()),
+        ];
+        for (old_path, new_path) in moves {
+            let new_path = Path::new(&new_path);
+            if !old_path.exists() || new_path.exists() {
+                continue;
+            }
+
+            if let Some(parent) = new_path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| AppError::io("Failed to create new directories.", e))?;
+            }
+            // Rename doesn't work across file systems, so copy file in that case.
+            if fs::rename(&old_path, new_path).is_err() {
+                fs::copy(&old_path, new_path)
+                    .map_err(|e| AppError::io("Failed to move file to new location.", e))?;
+                fs::remove_file(&old_path)
+                    .map_err(|e| AppError::io("Failed to remove file from old location.", e))?;
+            }
+            Term::info(&format!(
+                "Moved '{}' to '{}'.",
+                old_path.display(),
+                new_path.display()
+            ));
+        }
+        Ok(())
+    }
+
+    // Configuration for all users of the system.
+    pub fn get_system_config_path() -> String {
+        if cfg!(windows) {
+            let program_data =
+                env::var("PROGRAMDATA").unwrap_or_else(|_| String::from("C:\\ProgramData"));
+            return format!("{}\\kamiya\\config.toml", program_data);
+        }
+        String::from("/etc/kamiya/config.toml")
+    }
+
+    pub fn set_overrides(overrides: Vec<String>) {
+        *OVERRIDES.lock().unwrap() = overrides;
+    }
+
+    pub fn set_change(change: &str) {
+        *CHANGE.lock().unwrap() = change.to_string();
+    }
+
+    // Paths to configuration files in order of priority, from lowest to highest.
+    pub fn get_config_files() -> Vec<String> {
+        let mut files: Vec<String> = vec![Self::get_system_config_path(), Self::get_config_path()];
+        // Bad profile name is reported before anything is read from profile.
+        let profile: String = Self::get_profile();
+        if Self::validate_profile_name(&profile).is_ok() {
+            files.push(Self::get_profile_config_path(&profile));
+        }
+        files
+    }
+
+    // Read configuration file as TOML table. Missing file is the same as empty one.
+    pub fn read_config_file(path: &str) -> Result<toml::Value, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(content) => config::parse(&content),
+            Err(_) => Ok(toml::Value::Table(toml::Table::new())),
+        }
+    }
+
+    // Get aliases from configuration files. Aliases are needed before arguments are parsed,
+    // so broken files are skipped silently and reported later by the command itself.
+    pub fn load_aliases() -> BTreeMap<String, String> {
+        let mut aliases: BTreeMap<String, String> = BTreeMap::new();
+        if Self::check_home().is_err() {
+            return aliases;
+        }
+        for path in Self::get_config_files() {
+            let value = match Self::read_config_file(&path) {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+            if let Some(toml::Value::Table(table)) = value.get("aliases") {
+                for (name, command) in table {
+                    if let toml::Value::String(command) = command {
+                        aliases.insert(name.clone(), command.clone());
+                    }
+                }
+            }
+        }
+        aliases
+    }
+
+    // Read configuration file and fail if it's broken. Unknown options are only reported.
+    pub fn load_config_file(path: &str) -> Result<toml::Value, AppError> {
+        let value = Self::read_config_file(path).map_err(|e| {
+            AppError::parse(&format!(
+                "Failed to parse configuration file '{}'. {}",
+                path, e
+            ))
+            .with_hint("Use `kamiya config edit` to fix it.")
+        })?;
+
+        for problem in config::validate(&value) {
+            match problem {
+                ConfigError::UnknownKey(_) => Term::warn(&format!("{}: {}", path, problem)),
+                _ => {
+                    return Err(AppError::parse(&format!("{}: {}", path, problem))
+                        .with_hint("Use `kamiya config check` to find all problems."))
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    // Change option in configuration. Fails if option doesn't exist or value has wrong type.
+    pub fn set_option(config: &mut toml::Value, key: &str, value: &str) -> Result<(), AppError> {
+        let reference = toml::Value::try_from(Config::default()).unwrap();
+        config::set_key(config, &reference, key, value).map_err(|e| AppError::parse(&e.to_string()))
+    }
+
+    // Build configuration from all layers. Every next layer replaces options from previous one:
+    // built-in defaults, system file, user file, profile file, `KAMIYA_*` variables and `--set`.
+    // Configuration is built only once per run, so problems are reported only once.
+    pub fn load_config_value() -> Result<toml::Value, AppError> {
+        let mut cache = CONFIG.lock().unwrap();
+        if let Some(value) = cache.as_ref() {
+            return Ok(value.clone());
+        }
+
+        let defaults = toml::Value::try_from(Config::default()).unwrap();
+        let mut value = defaults.clone();
+        for path in Self::get_config_files() {
+            config::merge(&mut value, Self::load_config_file(&path)?);
+        }
+
+        for (key, _) in config::flatten(&defaults) {
+            if let Ok(env_value) = env::var(config::env_name(&key)) {
+                Self::set_option(&mut value, &key, &env_value)?;
+            }
+        }
+        // Editors for extensions can't be known in advance, so they are found by prefix.
+        for (variable, env_value) in env::vars() {
+            if let Some(extension) = variable.strip_prefix("KAMIYA_EDITORS_") {
+                let key = format!("editors.{}", extension.to_lowercase());
+                Self::set_option(&mut value, &key, &env_value)?;
+            }
+        }
+
+        for option in OVERRIDES.lock().unwrap().iter() {
+            match option.split_once('=') {
+                Some((key, option_value)) => {
+                    Self::set_option(&mut value, key.trim(), option_value)?
+                }
+                None => {
+                    return Err(AppError::general(&format!(
+                        "Expected `key=value`, got '{}'.",
+                        option
+                    )))
+                }
+            }
+        }
+        *cache = Some(value.clone());
+        Ok(value)
+    }
+
+    pub fn load_config() -> Result<Config, AppError> {
+        Self::load_config_value()?
+            .try_into()
+            .map_err(|e: toml::de::Error| {
+                AppError::parse(&format!("Bad configuration: {}", e.message()))
+            })
+    }
+
+    // Configuration for work which must not fail because of it, like loading database or running hooks.
+    // Broken configuration is reported by commands which need it.
+    pub fn load_config_or_default() -> Config {
+        Self::load_config().unwrap_or_default()
+    }
+
+    pub fn load_database() -> Result<Database, AppError> {
+        let path = Self::get_database_path();
+        let mut content = fs::read_to_string(&path)
+            .map_err(|e| AppError::io(&format!("Unable to read database '{}'.", path), e))?;
+        if crypto::is_encrypted(&content) {
+            let passphrase = Self::get_passphrase()?;
+            content = match crypto::decrypt(&content, &passphrase) {
+                Ok(data) => String::from_utf8(data)
+                    .map_err(|_| AppError::parse("Database is not valid UTF-8."))?,
+                Err(CryptoError::BadPassphrase) => {
+                    return Err(AppError::general("Wrong passphrase for the database."))
+                }
+                Err(CryptoError::BadFormat) => {
+                    return Err(AppError::parse("Encrypted database is corrupted."))
+                }
+                Err(e) => return Err(e.into()),
+            };
+            Self::set_passphrase(Some(passphrase));
         }
+        let mut database: Database = toml::from_str(&content).map_err(|e: toml::de::Error| {
+            AppError::parse(&format!(
+                "Failed to parse database '{}'. {}",
+                path,
+                e.message()
+            ))
+        })?;
+        database.set_ignore_case(Self::load_config_or_default().get_ignore_case());
+        database.assign_ids();
+        Ok(database)
+    }
+
+    // Write user configuration file.
+    pub fn write_config(cfg: toml::Value) -> Result<(), AppError> {
+        let config_string = toml::to_string(&cfg)
+            .map_err(|e| AppError::parse(&format!("Failed to serialize configuration. {}", e)))?;
+        fs::write(Self::get_config_path(), config_string)
+            .map_err(|e| AppError::io("Unable to write configuration file.", e))
     }
 
-    pub fn load_database() -> Database {
-        let content =
-            fs::read_to_string(Self::get_database_path()).expect("Unable to read the file.");
-        match toml::from_str(&content) {
-            Ok(cfg) => cfg,
-            Err(_) => panic!("Failed to parse database file."),
+    pub fn serialize_database(db: &Database) -> Result<String, AppError> {
+        toml::to_string(db)
+            .map_err(|e| AppError::parse(&format!("Failed to serialize database. {}", e)))
+    }
+
+    pub fn write_database(db: Database) -> Result<(), AppError> {
+        hooks::run("pre_write_database", None)?;
+        let mut config_string = Self::serialize_database(&db)?;
+        if let Some(passphrase) = PASSPHRASE.lock().unwrap().as_ref() {
+            config_string = crypto::encrypt(config_string.as_bytes(), passphrase)?;
         }
+        Self::write_private(
+            Path::new(&Self::get_database_path()),
+            config_string.as_bytes(),
+        )
+        .map_err(|e| AppError::io("Unable to write database file.", e))?;
+        // Database is already written, so failed commit is only reported.
+        if git::is_repo() {
+            if let Err(e) = git::commit(&CHANGE.lock().unwrap()) {
+                Term::warn(&format!("Changes have not been committed. {}", e));
+            }
+        }
+        hooks::run("post_write_database", None)
     }
 
-    pub fn write_config(cfg: Config) {
-        let config_string =
-            toml::to_string(&cfg).expect("Error when parsing the configuration file.");
-        fs::write(Self::get_config_path(), config_string).expect("Unable to write data to file.");
+    // Write file which only current user can read. Permissions of existing file are kept.
+    pub fn write_private(path: &Path, content: &[u8]) -> Result<(), io::Error> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        options.open(path)?.write_all(content)
     }
 
-    pub fn write_database(db: Database) {
-        let config_string = toml::to_string(&db).expect("Error when parsing the database file.");
-        fs::write(Self::get_database_path(), config_string).expect("Unable to write data to file.");
+    pub fn is_database_encrypted() -> bool {
+        match fs::read_to_string(Self::get_database_path()) {
+            Ok(content) => crypto::is_encrypted(&content),
+            Err(_) => false,
+        }
+    }
+
+    // Set passphrase for the database. `None` means database will be stored as plain text.
+    pub fn set_passphrase(passphrase: Option<String>) {
+        *PASSPHRASE.lock().unwrap() = passphrase;
+    }
+
+    // Get passphrase from `KAMIYA_PASSPHRASE` or ask user for it.
+    fn get_passphrase() -> Result<String, AppError> {
+        if let Some(passphrase) = PASSPHRASE.lock().unwrap().as_ref() {
+            return Ok(passphrase.clone());
+        }
+        match env::var("KAMIYA_PASSPHRASE") {
+            Ok(passphrase) => Ok(passphrase),
+            Err(_) => Term::ask_passphrase("Passphrase for the database"),
+        }
     }
 
     pub fn check_config() -> bool {
@@ -58,25 +561,31 @@ impl Manager {
         Path::new(&Self::get_database_path()).exists()
     }
 
-    pub fn make_default() {
-        if !Path::new(&Self::get_config_dir()).exists() {
-            fs::create_dir_all(Self::get_config_dir()).expect("Failed to create new directories.");
+    pub fn make_default() -> Result<(), AppError> {
+        for dir in [Self::get_config_dir(), Self::get_data_dir()] {
+            if !Path::new(&dir).exists() {
+                fs::create_dir_all(dir)
+                    .map_err(|e| AppError::io("Failed to create new directories.", e))?;
+            }
         }
 
         if !Path::new(&Self::get_config_path()).exists() {
             fs::write(
                 Self::get_config_path(),
-                toml::to_string(&Config::default()).unwrap(),
+                toml::to_string(&Config::default()).map_err(|e| {
+                    AppError::parse(&format!("Failed to serialize configuration. {}", e))
+                })?,
             )
-            .expect("Failed to create new config file.");
+            .map_err(|e| AppError::io("Failed to create new config file.", e))?;
         }
 
         if !Path::new(&Self::get_database_path()).exists() {
-            fs::write(
-                Self::get_database_path(),
-                toml::to_string(&Database::default()).unwrap(),
+            Self::write_private(
+                Path::new(&Self::get_database_path()),
+                Self::serialize_database(&Database::default())?.as_bytes(),
             )
-            .expect("Failed to create new database file.");
+            .map_err(|e| AppError::io("Failed to create new database file.", e))?;
         }
+        Ok(())
     }
 }
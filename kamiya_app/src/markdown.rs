@@ -0,0 +1,282 @@
+use kamiya_database::Note;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashSet;
+
+// Names that Windows doesn't allow for files.
+const RESERVED_NAMES: [&str; 22] = [
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+// Make name of the note safe to use as file name.
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.trim().to_lowercase().chars() {
+        if c.is_alphanumeric() || c == '_' || c == '.' {
+            slug.push(c);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    let slug = slug.trim_matches(|c| c == '-' || c == '.').to_string();
+    if slug.is_empty() {
+        return String::from("note");
+    }
+    // Windows reserves names with any extension too, like `con.txt`.
+    let (stem, extension) = slug.split_at(slug.find('.').unwrap_or(slug.len()));
+    if RESERVED_NAMES.contains(&stem) {
+        return format!("{}-note{}", stem, extension);
+    }
+    slug
+}
+
+// Get slug that is not taken yet and remember it.
+pub fn unique_slug(name: &str, taken: &mut HashSet<String>) -> String {
+    let slug = slugify(name);
+    let mut candidate = slug.clone();
+    let mut counter = 2;
+    while taken.contains(&candidate) {
+        candidate = format!("{}-{}", slug, counter);
+        counter += 1;
+    }
+    taken.insert(candidate.clone());
+    candidate
+}
+
+// Render note as markdown document with YAML front matter.
+pub fn render_note(note: &Note) -> String {
+    // JSON strings and arrays are valid YAML too.
+    let mut front_matter = format!(
+        "name: {}\ndescription: {}\n",
+        serde_json::to_string(&note.name).unwrap(),
+        serde_json::to_string(&note.description).unwrap(),
+    );
+    if !note.tags.is_empty() {
+        front_matter += &format!("tags: {}\n", serde_json::to_string(&note.tags).unwrap());
+    }
+    format!("---\n{}---\n{}", front_matter, note.content)
+}
+
+// Split markdown document into YAML front matter and the rest of content.
+pub fn split_front_matter(text: &str) -> (Option<Mapping>, &str) {
+    let rest = match text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    {
+        Some(rest) => rest,
+        None => return (None, text),
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            let body = &rest[offset + line.len()..];
+            return match serde_yaml::from_str::<Mapping>(&rest[..offset]) {
+                Ok(mapping) => (Some(mapping), body),
+                Err(_) if rest[..offset].trim().is_empty() => (Some(Mapping::new()), body),
+                Err(_) => (None, text),
+            };
+        }
+        offset += line.len();
+    }
+    (None, text)
+}
+
+// Get string field from front matter.
+pub fn get_field(front_matter: &Mapping, key: &str) -> Option<String> {
+    match front_matter.get(key) {
+        Some(Value::String(value)) => Some(value.clone()),
+        Some(Value::Number(value)) => Some(value.to_string()),
+        Some(Value::Bool(value)) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+// Get tags from front matter. They can be given as list or as string.
+pub fn get_tags(front_matter: &Mapping) -> Vec<String> {
+    let values: Vec<String> = match front_matter.get("tags") {
+        Some(Value::Sequence(items)) => items
+            .iter()
+            .filter_map(|item| match item {
+                Value::String(tag) => Some(tag.clone()),
+                Value::Number(tag) => Some(tag.to_string()),
+                _ => None,
+            })
+            .collect(),
+        Some(Value::String(tags)) => tags
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .map(String::from)
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in values {
+        let tag = tag.trim().trim_start_matches('#').to_string();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+// Make note from markdown document. Name and description are taken from front matter.
+pub fn parse_note(text: &str, default_name: &str) -> Note {
+    let (front_matter, body) = split_front_matter(text);
+    let front_matter = front_matter.unwrap_or_default();
+    Note {
+        id: String::new(),
+        name: get_field(&front_matter, "name").unwrap_or_else(|| default_name.to_string()),
+        content: body.to_string(),
+        description: get_field(&front_matter, "description").unwrap_or_default(),
+        tags: get_tags(&front_matter),
+        secret: false,
+        modified: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(name: &str, description: &str, content: &str) -> Note {
+        Note {
+            id: String::new(),
+            name: name.to_string(),
+            content: content.to_string(),
+            description: description.to_string(),
+            tags: Vec::new(),
+            secret: false,
+            modified: 0,
+        }
+    }
+
+    #[test]
+    fn slugs() {
+        assert_eq!(slugify("My Awesome Note"), "my-awesome-note");
+        assert_eq!(slugify("  Meeting: 2024/01/02!  "), "meeting-2024-01-02");
+        assert_eq!(slugify("Заметка №1"), "заметка-1");
+        assert_eq!(slugify("v1.2_final"), "v1.2_final");
+        assert_eq!(slugify("../secret"), "secret");
+        assert_eq!(slugify("///"), "note");
+        assert_eq!(slugify(""), "note");
+    }
+
+    #[test]
+    fn reserved_windows_names() {
+        assert_eq!(slugify("CON"), "con-note");
+        assert_eq!(slugify("nul"), "nul-note");
+        assert_eq!(slugify("Com1"), "com1-note");
+        assert_eq!(slugify("lpt9"), "lpt9-note");
+        assert_eq!(slugify("aux.txt"), "aux-note.txt");
+        assert_eq!(slugify("console"), "console");
+        assert_eq!(slugify("com10"), "com10");
+    }
+
+    #[test]
+    fn unique_slugs() {
+        let mut taken: HashSet<String> = HashSet::new();
+        assert_eq!(unique_slug("Note", &mut taken), "note");
+        // Case-insensitive file systems can't keep files which differ only in case.
+        assert_eq!(unique_slug("NOTE", &mut taken), "note-2");
+        assert_eq!(unique_slug("note!", &mut taken), "note-3");
+        assert_eq!(unique_slug("Note 2", &mut taken), "note-2-2");
+        assert_eq!(unique_slug("Other", &mut taken), "other");
+        assert_eq!(taken.len(), 5);
+    }
+
+    #[test]
+    fn front_matter() {
+        let (front_matter, body) = split_front_matter("---\nname: Test\n---\nBody\n---\n");
+        assert_eq!(get_field(&front_matter.unwrap(), "name").unwrap(), "Test");
+        assert_eq!(body, "Body\n---\n");
+
+        let (front_matter, body) = split_front_matter("---\nname: Test\n...\nBody");
+        assert!(front_matter.is_some());
+        assert_eq!(body, "Body");
+
+        let (front_matter, body) = split_front_matter("---\n---\nBody");
+        assert!(front_matter.unwrap().is_empty());
+        assert_eq!(body, "Body");
+    }
+
+    #[test]
+    fn front_matter_with_crlf() {
+        let text = "---\r\nname: Test\r\ndescription: Windows\r\n---\r\nBody\r\n";
+        let (front_matter, body) = split_front_matter(text);
+        let front_matter = front_matter.unwrap();
+        assert_eq!(get_field(&front_matter, "name").unwrap(), "Test");
+        assert_eq!(get_field(&front_matter, "description").unwrap(), "Windows");
+        assert_eq!(body, "Body\r\n");
+    }
+
+    #[test]
+    fn no_front_matter() {
+        for text in [
+            "Body",
+            "",
+            "--- not front matter\nBody",
+            "Body\n---\nname: Test\n---\n",
+            // Front matter is never closed.
+            "---\nname: Test\nBody",
+            // Invalid YAML is part of the content.
+            "---\nname: [unclosed\n---\nBody",
+            "---\njust text\n---\nBody",
+        ] {
+            let (front_matter, body) = split_front_matter(text);
+            assert!(front_matter.is_none(), "{:?}", text);
+            assert_eq!(body, text);
+        }
+    }
+
+    #[test]
+    fn parse_notes() {
+        let note = parse_note(
+            "---\nname: Plan\ndescription: For today\ntags: [work, \"#todo\", work]\n---\nBuy milk\n",
+            "plan",
+        );
+        assert_eq!(note.name, "Plan");
+        assert_eq!(note.description, "For today");
+        assert_eq!(note.tags, ["work", "todo"]);
+        assert_eq!(note.content, "Buy milk\n");
+        assert!(!note.secret);
+
+        assert_eq!(
+            parse_note("---\ntags: a, b c\n---\n", "x").tags,
+            ["a", "b", "c"]
+        );
+        assert_eq!(parse_note("---\nname: 2024\n---\n", "x").name, "2024");
+    }
+
+    #[test]
+    fn parse_note_falls_back_to_whole_text() {
+        let text = "---\nname: [unclosed\n---\nBody";
+        let note = parse_note(text, "file name");
+        assert_eq!(note.name, "file name");
+        assert_eq!(note.description, "");
+        assert_eq!(note.content, text);
+    }
+
+    #[test]
+    fn rendered_note_is_parsed_back() {
+        let mut original = note(
+            "Meeting: \"Q1\" #1",
+            "Notes from 'meeting' — ü\\n",
+            "---\nNot front matter\n---\n",
+        );
+        original.tags = vec![String::from("work"), String::from("2024-review")];
+
+        let parsed = parse_note(&render_note(&original), "file name");
+        assert_eq!(parsed.name, original.name);
+        assert_eq!(parsed.description, original.description);
+        assert_eq!(parsed.tags, original.tags);
+        assert_eq!(parsed.content, original.content);
+
+        let parsed = parse_note(&render_note(&note("Empty", "", "")), "file name");
+        assert_eq!(parsed.name, "Empty");
+        assert_eq!(parsed.description, "");
+        assert_eq!(parsed.content, "");
+    }
+}
@@ -0,0 +1,181 @@
+use crate::markdown;
+use kamiya_database::Note;
+use kamiya_utils::walk::walk_dir;
+use std::{
+    fs,
+    io::Error,
+    path::{Path, PathBuf},
+};
+
+// Result of reading Obsidian vault.
+pub struct Vault {
+    pub notes: Vec<Note>,
+    pub attachments: Vec<PathBuf>,
+    pub unreadable: Vec<PathBuf>,
+}
+
+// Read all notes from Obsidian vault. Files that are not notes are collected as attachments.
+pub fn read_vault(path: &Path) -> Result<Vault, Error> {
+    let mut vault = Vault {
+        notes: Vec::new(),
+        attachments: Vec::new(),
+        unreadable: Vec::new(),
+    };
+
+    for file in walk_dir(path)? {
+        let relative = file.strip_prefix(path).unwrap_or(&file).to_path_buf();
+        let is_note = relative
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("md"));
+        if !is_note {
+            vault.attachments.push(relative);
+            continue;
+        }
+
+        let content = match fs::read_to_string(&file) {
+            Ok(content) => content,
+            Err(_) => {
+                vault.unreadable.push(relative);
+                continue;
+            }
+        };
+
+        // Folders of the vault become prefixes of note name, like `Projects/Kamiya`.
+        let name = relative
+            .with_extension("")
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<String>>()
+            .join("/");
+        let mut note = markdown::parse_note(&content, &name);
+        for tag in find_tags(&note.content) {
+            if !note.tags.contains(&tag) {
+                note.tags.push(tag);
+            }
+        }
+        vault.notes.push(note);
+    }
+    Ok(vault)
+}
+
+// Find inline `#tags` in note content, ignoring headings and code blocks.
+pub fn find_tags(content: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    // Code block is closed only by the same fence it was opened with.
+    let mut fence: Option<&str> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            match fence {
+                None => fence = Some(marker),
+                Some(open) if open == marker => fence = None,
+                Some(_) => {}
+            }
+            continue;
+        }
+        if fence.is_some() {
+            continue;
+        }
+
+        let mut previous = ' ';
+        let mut in_code = false;
+        for (index, c) in line.char_indices() {
+            if c == '`' {
+                in_code = !in_code;
+            }
+            if c == '#' && !in_code && previous.is_whitespace() {
+                let tag: String = line[index + 1..]
+                    .chars()
+                    .take_while(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '/'))
+                    .collect();
+                // Tags must have at least one non-numeric character.
+                if tag.chars().any(|c| !c.is_numeric()) && !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            previous = c;
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    #[test]
+    fn tags_in_text() {
+        assert_eq!(
+            find_tags("#start of line, in #middle and #nested/tag-name_2.\n#start again"),
+            ["start", "middle", "nested/tag-name_2"]
+        );
+        assert_eq!(find_tags("#тег and #タグ"), ["тег", "タグ"]);
+        assert_eq!(find_tags("#same #same"), ["same"]);
+    }
+
+    #[test]
+    fn not_tags() {
+        assert!(find_tags("# Heading\n## Subheading\n###### Deep").is_empty());
+        assert!(find_tags("issue#12, url.com/#anchor, C#").is_empty());
+        assert!(find_tags("#123 and #2024").is_empty());
+        assert_eq!(find_tags("#2024-review"), ["2024-review"]);
+    }
+
+    #[test]
+    fn no_tags_in_code() {
+        assert_eq!(find_tags("`#inline` and #outside `code #x`"), ["outside"]);
+        assert_eq!(
+            find_tags("#before\n```sh\n# comment #fenced\n```\n#after"),
+            ["before", "after"]
+        );
+        assert_eq!(
+            find_tags("~~~\n#tilde\n```\n#still_code\n~~~\n#after"),
+            ["after"]
+        );
+        assert!(find_tags("  ```\n  #indented\n  ```").is_empty());
+    }
+
+    #[test]
+    fn vault_notes_and_attachments() {
+        let path = env::temp_dir().join(format!("kamiya-vault-{}", process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(path.join("Projects")).unwrap();
+        fs::write(path.join("Inbox.md"), "Text #todo").unwrap();
+        fs::write(
+            path.join("Projects").join("Kamiya.MD"),
+            "---\ntags: [rust]\n---\n#rust and #cli",
+        )
+        .unwrap();
+        fs::write(path.join("Projects").join("logo.png"), [0u8, 1, 2]).unwrap();
+        fs::write(path.join("README"), "no extension").unwrap();
+
+        let vault = read_vault(&path).unwrap();
+        fs::remove_dir_all(&path).unwrap();
+
+        let mut notes: Vec<(String, Vec<String>)> = vault
+            .notes
+            .into_iter()
+            .map(|note| (note.name, note.tags))
+            .collect();
+        notes.sort();
+        assert_eq!(
+            notes,
+            [
+                (String::from("Inbox"), vec![String::from("todo")]),
+                (
+                    String::from("Projects/Kamiya"),
+                    vec![String::from("rust"), String::from("cli")]
+                ),
+            ]
+        );
+        let mut attachments = vault.attachments;
+        attachments.sort();
+        assert_eq!(
+            attachments,
+            [PathBuf::from("Projects/logo.png"), PathBuf::from("README")]
+        );
+        assert!(vault.unreadable.is_empty());
+    }
+}
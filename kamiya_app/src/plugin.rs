@@ -0,0 +1,165 @@
+use crate::{error::AppError, hooks, manager::Manager};
+use kamiya_database::{Database, Note, OnConflict};
+use kamiya_utils::crypto;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+
+// Version of the protocol. Changes which break plugins must increase it.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+enum Conflict {
+    #[default]
+    Fail,
+    Overwrite,
+    Suffix,
+}
+
+impl From<Conflict> for OnConflict {
+    fn from(conflict: Conflict) -> Self {
+        match conflict {
+            Conflict::Fail => OnConflict::Fail,
+            Conflict::Overwrite => OnConflict::Overwrite,
+            Conflict::Suffix => OnConflict::Suffix,
+        }
+    }
+}
+
+// Request of plugin. Every request is single line of JSON, like `{"id": 1, "method": "list"}`.
+#[derive(Deserialize)]
+#[serde(tag = "method", rename_all = "lowercase")]
+enum Request {
+    Info {},
+    List {},
+    Get {
+        name: String,
+    },
+    Put {
+        note: Note,
+        #[serde(default)]
+        on_conflict: Conflict,
+    },
+    Rename {
+        name: String,
+        new_name: String,
+        #[serde(default)]
+        on_conflict: Conflict,
+    },
+    Delete {
+        name: String,
+    },
+}
+
+fn handle(request: Request) -> Result<Value, AppError> {
+    match request {
+        Request::Info {} => Ok(json!({
+            "protocol": PROTOCOL_VERSION,
+            "version": env!("CARGO_PKG_VERSION"),
+            "profile": Manager::get_profile(),
+            "data_dir": Manager::get_data_dir(),
+        })),
+        Request::List {} => {
+            let database: Database = Manager::load_database()?;
+            let notes: Vec<Value> = database
+                .get_notes()
+                .iter()
+                .map(|note| {
+                    json!({
+                        "name": note.name,
+                        "description": note.description,
+                        "tags": note.tags,
+                        "id": note.id,
+                        "secret": note.secret,
+                        "modified": note.modified,
+                    })
+                })
+                .collect();
+            Ok(Value::Array(notes))
+        }
+        Request::Get { name } => {
+            let database: Database = Manager::load_database()?;
+            Ok(json!(database.get_note(&name)?))
+        }
+        Request::Put { note, on_conflict } => {
+            if note.name.trim().is_empty() {
+                return Err(AppError::parse("Note must have a name."));
+            }
+            // Plugins can't encrypt notes, so secret note must come from `get` as it is.
+            if note.secret && !crypto::is_encrypted(&note.content) {
+                return Err(AppError::parse("Content of secret note must be encrypted."));
+            }
+            let mut database: Database = Manager::load_database()?;
+            // Replaced note is edited, like with `open`. Otherwise note is new, like with `take`.
+            let event: &str = match on_conflict {
+                Conflict::Overwrite if database.note_exists(&note.name) => "post_edit",
+                _ => "post_take",
+            };
+            let name: String = database.insert_note(note, on_conflict.into())?;
+            let note: Note = database.get_note(&name)?;
+            Manager::set_change(&format!("plugin-api put: {}", name));
+            Manager::write_database(database)?;
+            hooks::run(event, Some(&note))?;
+            Ok(json!({ "name": name }))
+        }
+        Request::Rename {
+            name,
+            new_name,
+            on_conflict,
+        } => {
+            let mut database: Database = Manager::load_database()?;
+            let new_name: String = database.rename_note(&name, &new_name, on_conflict.into())?;
+            Manager::set_change(&format!("plugin-api rename: {} -> {}", name, new_name));
+            Manager::write_database(database)?;
+            Ok(json!({ "name": new_name }))
+        }
+        Request::Delete { name } => {
+            let mut database: Database = Manager::load_database()?;
+            let note: Note = database.get_note(&name)?;
+            database.remove_note(&name)?;
+            Manager::set_change(&format!("plugin-api delete: {}", name));
+            Manager::write_database(database)?;
+            hooks::run("post_delete", Some(&note))?;
+            Ok(Value::Null)
+        }
+    }
+}
+
+// Build response for single line of input. Errors have the same codes as exit codes of Kamiya.
+fn respond(line: &str) -> Value {
+    let (id, result) = match serde_json::from_str::<Value>(line) {
+        Ok(value) => {
+            let id: Value = value.get("id").cloned().unwrap_or(Value::Null);
+            let result = serde_json::from_value::<Request>(value)
+                .map_err(|e| AppError::parse(&format!("Bad request: {}", e)))
+                .and_then(handle);
+            (id, result)
+        }
+        Err(e) => (
+            Value::Null,
+            Err(AppError::parse(&format!("Bad JSON: {}", e))),
+        ),
+    };
+    match result {
+        Ok(result) => json!({ "id": id, "result": result }),
+        Err(e) => json!({
+            "id": id,
+            "error": { "code": e.exit_code(), "message": e.to_string() },
+        }),
+    }
+}
+
+// Answer requests from stdin until it's closed. Every response is written as single line.
+pub fn serve() -> Result<(), io::Error> {
+    let mut stdout = io::stdout();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        writeln!(stdout, "{}", respond(&line))?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
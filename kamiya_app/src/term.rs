@@ -1,33 +1,79 @@
-use std::io::{self, Write};
+use crate::error::AppError;
+use std::{
+    io::{self, IsTerminal, Write},
+    sync::Mutex,
+};
+use terminal_size::{terminal_size, Height, Width};
 
 pub enum AskDefaultAnswers {
     Yes,
     No,
 }
 
+// Where messages are printed. Commands which print data for programs keep stdout for it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Output {
+    Stdout,
+    Stderr,
+    Silent,
+}
+
+static OUTPUT: Mutex<Output> = Mutex::new(Output::Stdout);
+
 pub struct Term;
 impl Term {
+    pub fn set_output(output: Output) {
+        *OUTPUT.lock().unwrap() = output;
+    }
+
+    pub fn get_output() -> Output {
+        *OUTPUT.lock().unwrap()
+    }
+
+    fn print(line: &str) {
+        match Self::get_output() {
+            Output::Stdout => println!("{}", line),
+            Output::Stderr => eprintln!("{}", line),
+            Output::Silent => {}
+        }
+    }
+
     pub fn success(msg: &str) {
-        println!("\x1b[1m\x1b[92m \x1b[0m\x1b[1m {}\x1b[0m", msg);
+        Self::print(&format!("\x1b[1m\x1b[92m \x1b[0m\x1b[1m {}\x1b[0m", msg));
     }
 
     pub fn title(msg: &str) {
-        println!("\x1b[1m\x1b[92m \x1b[0m\x1b[1m {}\x1b[0m", msg);
+        Self::print(&Self::format_title(msg));
+    }
+
+    pub fn format_title(msg: &str) -> String {
+        format!("\x1b[1m\x1b[92m \x1b[0m\x1b[1m {}\x1b[0m", msg)
     }
 
     pub fn list_item(name: &str, desc: &str) {
-        println!("  \x1b[1m {}\x1b[0m {}", name, desc);
+        Self::print(&Self::format_list_item(name, desc));
+    }
+
+    pub fn format_list_item(name: &str, desc: &str) -> String {
+        format!("  \x1b[1m {}\x1b[0m {}", name, desc)
     }
 
     pub fn message(msg: &str) {
-        println!(" \x1b[1m 󰍡 {}\x1b[0m", msg);
+        Self::print(&Self::format_message(msg));
+    }
+
+    pub fn format_message(msg: &str) -> String {
+        format!(" \x1b[1m 󰍡 {}\x1b[0m", msg)
     }
 
     pub fn hint(msg: &str) {
-        println!("\x1b[1m 󰌵 {}\x1b[0m", msg);
+        Self::print(&format!("\x1b[1m 󰌵 {}\x1b[0m", msg));
     }
 
-    pub fn ask_yn(msg: &str, default_answer: AskDefaultAnswers) -> AskDefaultAnswers {
+    pub fn ask_yn(
+        msg: &str,
+        default_answer: AskDefaultAnswers,
+    ) -> Result<AskDefaultAnswers, AppError> {
         let default_answer_display = match default_answer {
             AskDefaultAnswers::Yes => "(Y/n)",
             AskDefaultAnswers::No => "(y/N)",
@@ -36,36 +82,91 @@ impl Term {
         print!("  \x1b[1m{} {}:\x1b[0m ", msg, default_answer_display);
         io::stdout()
             .flush()
-            .expect("Failed to push text to stdout.");
+            .map_err(|e| AppError::io("Failed to write question.", e))?;
         let mut answer = String::new();
         io::stdin()
             .read_line(&mut answer)
-            .expect("Failed to read stdin.");
+            .map_err(|e| AppError::io("Failed to read answer.", e))?;
         answer = answer.to_lowercase().trim().to_string();
         if answer.is_empty() {
-            return default_answer;
+            return Ok(default_answer);
         }
 
-        match answer.as_str() {
+        Ok(match answer.as_str() {
             "y" => AskDefaultAnswers::Yes,
             "n" => AskDefaultAnswers::No,
             _ => default_answer,
-        }
+        })
+    }
+
+    pub fn ask_passphrase(msg: &str) -> Result<String, AppError> {
+        rpassword::prompt_password(format!("  \x1b[1m{}:\x1b[0m ", msg))
+            .map_err(|e| AppError::io("Failed to read passphrase.", e))
     }
 
     pub fn info(msg: &str) {
-        println!("\x1b[1m \x1b[0m\x1b[1m {}\x1b[0m", msg);
+        Self::print(&format!("\x1b[1m \x1b[0m\x1b[1m {}\x1b[0m", msg));
     }
 
     pub fn work(msg: &str) {
-        println!("\x1b[1m \x1b[0m\x1b[1m {}\x1b[0m", msg);
+        Self::print(&format!("\x1b[1m \x1b[0m\x1b[1m {}\x1b[0m", msg));
     }
 
     pub fn warn(msg: &str) {
-        println!("\x1b[1m\x1b[93m \x1b[0m\x1b[1m {}\x1b[0m", msg);
+        Self::print(&format!("\x1b[1m\x1b[93m \x1b[0m\x1b[1m {}\x1b[0m", msg));
     }
 
     pub fn fatal(msg: &str) {
-        println!("\x1b[1m\x1b[91m \x1b[0m\x1b[1m {}\x1b[0m", msg);
+        Self::print(&format!("\x1b[1m\x1b[91m \x1b[0m\x1b[1m {}\x1b[0m", msg));
+    }
+
+    pub fn is_interactive() -> bool {
+        io::stdout().is_terminal()
+    }
+
+    // Width and height of the terminal.
+    pub fn size() -> Option<(usize, usize)> {
+        terminal_size().map(|(Width(w), Height(h))| (w as usize, h as usize))
+    }
+
+    // Number of rows which text takes on terminal of given width, with long lines wrapped.
+    pub fn count_rows(text: &str, width: usize) -> usize {
+        text.lines()
+            .map(|line| display_width(line).div_ceil(width.max(1)).max(1))
+            .sum()
+    }
+}
+
+// Number of columns which text takes. Escape sequences take no space, wide characters take two.
+fn display_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Sequences like `\x1b[1m` end with a letter.
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        width += match c as u32 {
+            0x1100..=0x115F
+            | 0x2E80..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F300..=0x1F64F
+            | 0x1F900..=0x1F9FF
+            | 0x20000..=0x3FFFD => 2,
+            _ if c.is_control() => 0,
+            _ => 1,
+        };
     }
+    width
 }
@@ -0,0 +1,56 @@
+// Every test uses only some of these helpers.
+#![allow(dead_code)]
+
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{self, Command},
+};
+
+// Directory with configuration and database, removed after test.
+pub struct Home {
+    pub path: PathBuf,
+}
+
+impl Home {
+    pub fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("kamiya-test-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Home { path }
+    }
+
+    pub fn write_config(&self, content: &str) {
+        fs::write(self.path.join("config.toml"), content).unwrap();
+    }
+
+    // Kamiya which uses this directory and ignores `KAMIYA_*` variables of the environment.
+    pub fn kamiya(&self) -> Command {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_kamiya"));
+        for (key, _) in env::vars_os() {
+            if key.to_string_lossy().starts_with("KAMIYA_") {
+                cmd.env_remove(key);
+            }
+        }
+        cmd.env("KAMIYA_HOME", &self.path);
+        cmd
+    }
+
+    // Run Kamiya and check that it succeeded.
+    pub fn run(&self, args: &[&str]) {
+        let output = self.kamiya().args(args).output().unwrap();
+        assert!(
+            output.status.success(),
+            "kamiya {:?} failed: {}{}",
+            args,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+impl Drop for Home {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
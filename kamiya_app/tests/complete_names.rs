@@ -0,0 +1,38 @@
+mod common;
+
+use common::Home;
+
+#[test]
+fn prints_only_names() {
+    let home = Home::new("complete-names");
+    home.run(&["take", "--name", "First", "content"]);
+    home.run(&["take", "--name", "Second note", "content"]);
+    // Unknown option makes Kamiya print warning.
+    home.write_config("bogus = 1\n");
+
+    let output = home
+        .kamiya()
+        .args(["__complete-names", "--", "get"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut names: Vec<&str> = stdout.lines().collect();
+    names.sort();
+    assert_eq!(names, ["First", "Second note"]);
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn fails_when_name_is_not_wanted() {
+    let home = Home::new("complete-options");
+    home.write_config("bogus = 1\n");
+
+    let output = home
+        .kamiya()
+        .args(["__complete-names", "--", "take"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+}
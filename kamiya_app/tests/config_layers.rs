@@ -0,0 +1,79 @@
+mod common;
+
+use common::Home;
+use std::fs;
+
+// Get option as Kamiya sees it with given environment variables and arguments.
+fn get(home: &Home, env: &[(&str, &str)], args: &[&str], key: &str) -> String {
+    let output = home
+        .kamiya()
+        .envs(env.iter().copied())
+        .args(args)
+        .args(["config", "get", key])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "config get {} failed", key);
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .trim_end()
+        .to_string()
+}
+
+#[test]
+fn every_layer_overrides_previous() {
+    let home = Home::new("config-layers");
+    home.run(&["profile", "create", "work"]);
+    home.write_config(
+        "[options]\neditor = \"user\"\npager = \"user\"\nname_template = \"User&i\"\n",
+    );
+    fs::write(
+        home.path.join("profiles").join("work").join("config.toml"),
+        "[options]\neditor = \"profile\"\npager = \"profile\"\n",
+    )
+    .unwrap();
+
+    let env = [("KAMIYA_EDITOR", "env")];
+    let profile = ["--profile", "work"];
+    let set = ["--profile", "work", "--set", "editor=set"];
+
+    assert_eq!(get(&home, &[], &[], "editor"), "user");
+    assert_eq!(get(&home, &[], &[], "hooks.timeout"), "30");
+    assert_eq!(get(&home, &[], &profile, "editor"), "profile");
+    assert_eq!(get(&home, &[], &profile, "name_template"), "User&i");
+    assert_eq!(get(&home, &env, &profile, "editor"), "env");
+    assert_eq!(get(&home, &env, &profile, "pager"), "profile");
+    assert_eq!(get(&home, &env, &set, "editor"), "set");
+}
+
+#[test]
+fn environment_sets_options_of_every_table() {
+    let home = Home::new("config-env");
+    home.write_config("[hooks]\ntimeout = 10\n");
+
+    let env = [("KAMIYA_HOOKS_TIMEOUT", "5"), ("KAMIYA_IGNORE_CASE", "yes")];
+    assert_eq!(get(&home, &env, &[], "hooks.timeout"), "5");
+    assert_eq!(get(&home, &env, &[], "ignore_case"), "true");
+
+    let output = home
+        .kamiya()
+        .env("KAMIYA_HOOKS_TIMEOUT", "soon")
+        .args(["config", "get", "hooks.timeout"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn editors_are_found_by_prefix() {
+    let home = Home::new("config-editors");
+    home.write_config("[editors]\nmd = \"user\"\ntxt = \"user\"\n");
+
+    let env = [("KAMIYA_EDITORS_MD", "env"), ("KAMIYA_EDITORS_RS", "rust")];
+    assert_eq!(get(&home, &env, &[], "editors.md"), "env");
+    assert_eq!(get(&home, &env, &[], "editors.rs"), "rust");
+    assert_eq!(get(&home, &env, &[], "editors.txt"), "user");
+    assert_eq!(
+        get(&home, &env, &["--set", "editors.md=set"], "editors.md"),
+        "set"
+    );
+}
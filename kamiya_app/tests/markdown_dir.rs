@@ -0,0 +1,80 @@
+mod common;
+
+use common::Home;
+use serde_json::Value;
+use std::{fs, io::Write, process::Stdio};
+
+// Get all notes with content through plugin API, sorted by name.
+fn notes(home: &Home) -> Vec<(String, String, String)> {
+    let mut child = home
+        .kamiya()
+        .arg("plugin-api")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    writeln!(stdin, r#"{{"id": 0, "method": "list"}}"#).unwrap();
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    let response: Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    let mut notes: Vec<(String, String, String)> = Vec::new();
+    for note in response["result"].as_array().unwrap() {
+        let name = note["name"].as_str().unwrap().to_string();
+        let output = home
+            .kamiya()
+            .args(["get", "--no-pager", &name])
+            .output()
+            .unwrap();
+        let content = String::from_utf8(output.stdout).unwrap();
+        let description = note["description"].as_str().unwrap().to_string();
+        notes.push((name, description, content));
+    }
+    notes.sort();
+    notes
+}
+
+#[test]
+fn export_and_import_keep_notes() {
+    let source = Home::new("markdown-source");
+    for (name, description, content) in [
+        (
+            "Meeting: \"Q1\" #1",
+            "From 'meeting' — ü",
+            "---\nNot front matter\n---\n",
+        ),
+        ("Note", "", "first"),
+        ("NOTE", "Same slug", "second"),
+        ("con", "Reserved on Windows", "third"),
+        ("Заметка", "yes", "четвёртая"),
+    ] {
+        source.run(&["take", "--name", name, "--desc", description, "--", content]);
+    }
+    let dir = source.path.join("exported");
+    let dir = dir.to_str().unwrap();
+    source.run(&["export", "--format", "markdown", "--path", dir]);
+
+    let mut files: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    files.sort();
+    assert_eq!(
+        files,
+        [
+            "con-note.md",
+            "meeting-q1-1.md",
+            "note-2.md",
+            "note.md",
+            "заметка.md"
+        ]
+    );
+
+    let target = Home::new("markdown-target");
+    target.run(&["import", "--from-dir", dir]);
+
+    let expected = notes(&source);
+    assert_eq!(expected.len(), 5);
+    assert_eq!(notes(&target), expected);
+}
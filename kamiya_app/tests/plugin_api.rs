@@ -0,0 +1,66 @@
+mod common;
+
+use common::Home;
+use serde_json::Value;
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+fn serve(mut cmd: Command, requests: &[&str]) -> (String, String) {
+    let mut child = cmd
+        .arg("plugin-api")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    for request in requests {
+        writeln!(stdin, "{}", request).unwrap();
+    }
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn stdout_has_only_responses() {
+    let home = Home::new("plugin-api");
+    // Unknown option and failed hooks make Kamiya print warnings.
+    home.write_config(
+        "bogus = 1\n\n[hooks]\npost_write_database = \"exit 3\"\npost_delete = \"echo deleted\"\n",
+    );
+    home.run(&["take", "--name", "Note2", "content"]);
+
+    let requests = [
+        r#"{"id": 1, "method": "info"}"#,
+        r#"{"id": 2, "method": "delete", "name": "Note2"}"#,
+        r#"{"id": 3, "method": "put", "note": {"name": "a", "content": "b"}}"#,
+        r#"{"id": 4, "method": "rename", "name": "a", "new_name": "c"}"#,
+        r#"{"id": 5, "method": "get", "name": "missing"}"#,
+        r#"{"id": 6, "method": "list"}"#,
+        "not json",
+    ];
+    let (stdout, stderr) = serve(home.kamiya(), &requests);
+
+    let responses: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("stdout must have only JSON"))
+        .collect();
+    assert_eq!(responses.len(), requests.len());
+    for (index, response) in responses.iter().take(6).enumerate() {
+        assert_eq!(response["id"], index + 1);
+    }
+    assert_eq!(responses[3]["result"]["name"], "c");
+    assert_eq!(responses[4]["error"]["code"], 3);
+    assert_eq!(responses[6]["error"]["code"], 6);
+
+    assert!(stderr.contains("Unknown option 'bogus'"));
+    assert!(stderr.contains("post_write_database"));
+    assert!(stderr.contains("deleted"));
+}
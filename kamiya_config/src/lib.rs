@@ -1,22 +1,97 @@
 use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, error::Error, fmt};
+use toml::Value;
 
+// Every option has default value, so configuration may contain only changed options.
 #[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Options {
     name_template: String,
     editor: String,
+    pager: String,
+    // Match note names ignoring case and Unicode normal form.
+    ignore_case: bool,
 }
+// Shell commands which are run on events. Empty command means there is no hook.
 #[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hooks {
+    post_take: String,
+    post_edit: String,
+    post_delete: String,
+    pre_write_database: String,
+    post_write_database: String,
+    // Seconds to wait for hook before it's killed. Zero means no limit.
+    timeout: i64,
+    // Cancel operation if pre-hook fails. Otherwise failure is only reported.
+    abort_on_failure: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct Config {
     options: Options,
+    hooks: Hooks,
+    // Editors for notes with specific extensions, like `md = "code --wait"`.
+    #[serde(default)]
+    editors: BTreeMap<String, String>,
+    // Commands which are expanded to other commands, like `todo = "open TODO"`.
+    #[serde(default)]
+    aliases: BTreeMap<String, String>,
 }
 
-impl Default for Config {
+// Tables which can have any keys with string values.
+const FREE_TABLES: [&str; 2] = ["editors", "aliases"];
+
+// Errors which may occur.
+#[derive(Debug)]
+pub enum ConfigError {
+    UnknownKey(String),
+    BadValue(String),
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::UnknownKey(key) => write!(f, "Unknown option '{}'.", key),
+            ConfigError::BadValue(key) => write!(f, "Bad value for option '{}'.", key),
+            ConfigError::Parse {
+                line,
+                column,
+                message,
+            } => write!(f, "Line {}, column {}: {}", line, column, message),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+impl Default for Options {
     fn default() -> Self {
-        Config {
-            options: Options {
-                name_template: String::from("Note&i"),
-                editor: String::from("nano"),
-            },
+        Options {
+            name_template: String::from("Note&i"),
+            editor: String::new(),
+            pager: String::new(),
+            ignore_case: false,
+        }
+    }
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Hooks {
+            post_take: String::new(),
+            post_edit: String::new(),
+            post_delete: String::new(),
+            pre_write_database: String::new(),
+            post_write_database: String::new(),
+            timeout: 30,
+            abort_on_failure: true,
         }
     }
 }
@@ -37,4 +112,369 @@ impl Config {
     pub fn set_editor(&mut self, editor: &str) {
         self.options.editor = String::from(editor);
     }
+
+    // Get editor for notes with given extension, or default editor if it's not set.
+    pub fn get_editor_for(&self, extension: &str) -> String {
+        match self.editors.get(extension) {
+            Some(editor) => editor.clone(),
+            None => self.get_editor(),
+        }
+    }
+
+    // Get command of hook for event, like `post_take`.
+    pub fn get_hook(&self, event: &str) -> String {
+        match event {
+            "post_take" => self.hooks.post_take.clone(),
+            "post_edit" => self.hooks.post_edit.clone(),
+            "post_delete" => self.hooks.post_delete.clone(),
+            "pre_write_database" => self.hooks.pre_write_database.clone(),
+            "post_write_database" => self.hooks.post_write_database.clone(),
+            _ => String::new(),
+        }
+    }
+
+    pub fn get_hook_timeout(&self) -> u64 {
+        self.hooks.timeout.max(0) as u64
+    }
+
+    pub fn get_abort_on_failure(&self) -> bool {
+        self.hooks.abort_on_failure
+    }
+
+    pub fn get_pager(&self) -> String {
+        self.options.pager.clone()
+    }
+
+    pub fn get_ignore_case(&self) -> bool {
+        self.options.ignore_case
+    }
+}
+
+// Get full key of option. Keys without table refer to `options`, so `editor` is `options.editor`.
+pub fn normalize_key(key: &str) -> String {
+    match key.contains('.') {
+        true => key.to_string(),
+        false => format!("options.{}", key),
+    }
+}
+
+// Get name of environment variable for option, like `KAMIYA_EDITOR` for `options.editor`
+// and `KAMIYA_EDITORS_MD` for `editors.md`.
+pub fn env_name(key: &str) -> String {
+    let key = normalize_key(key);
+    let key = key.strip_prefix("options.").unwrap_or(&key);
+    format!("KAMIYA_{}", key.replace('.', "_").to_uppercase())
+}
+
+// Recursively replace values in configuration with values from overrides.
+pub fn merge(config: &mut Value, overrides: Value) {
+    match (config, overrides) {
+        (Value::Table(config), Value::Table(overrides)) => {
+            for (key, value) in overrides {
+                match config.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        config.insert(key, value);
+                    }
+                }
+            }
+        }
+        (config, overrides) => *config = overrides,
+    }
+}
+
+// Get value of option by its key.
+pub fn get_key<'a>(config: &'a Value, key: &str) -> Option<&'a Value> {
+    let mut value = config;
+    for part in normalize_key(key).split('.') {
+        value = value.get(part)?;
+    }
+    Some(value)
+}
+
+// Set option from string. Value is converted to the type of option in `reference`,
+// which should contain all known options.
+pub fn set_key(
+    config: &mut Value,
+    reference: &Value,
+    key: &str,
+    raw: &str,
+) -> Result<(), ConfigError> {
+    let key = normalize_key(key);
+    let (table_name, option) = key.split_once('.').unwrap();
+    // Only `editors` and `aliases` tables can have new keys.
+    let value = match get_key(reference, &key) {
+        Some(Value::Boolean(_)) => match raw {
+            "true" | "yes" | "on" | "1" => Value::Boolean(true),
+            "false" | "no" | "off" | "0" => Value::Boolean(false),
+            _ => return Err(ConfigError::BadValue(key)),
+        },
+        Some(Value::Integer(_)) => match raw.parse::<i64>() {
+            Ok(number) => Value::Integer(number),
+            Err(_) => return Err(ConfigError::BadValue(key)),
+        },
+        Some(Value::String(_)) => Value::String(raw.to_string()),
+        None if FREE_TABLES.contains(&table_name) && !option.contains('.') => {
+            Value::String(raw.to_string())
+        }
+        _ => return Err(ConfigError::UnknownKey(key)),
+    };
+
+    if !config.is_table() {
+        *config = Value::Table(toml::Table::new());
+    }
+    let table = config
+        .as_table_mut()
+        .unwrap()
+        .entry(table_name)
+        .or_insert_with(|| Value::Table(toml::Table::new()));
+    match table.as_table_mut() {
+        Some(table) => {
+            table.insert(option.to_string(), value);
+            Ok(())
+        }
+        None => Err(ConfigError::BadValue(table_name.to_string())),
+    }
+}
+
+// Get all options with their values as list of keys and values.
+pub fn flatten(config: &Value) -> Vec<(String, String)> {
+    let mut options: Vec<(String, String)> = Vec::new();
+    if let Some(tables) = config.as_table() {
+        for (table_name, table) in tables {
+            match table.as_table() {
+                Some(table) => {
+                    for (option, value) in table {
+                        options.push((format!("{}.{}", table_name, option), display(value)));
+                    }
+                }
+                None => options.push((table_name.clone(), display(table))),
+            }
+        }
+    }
+    options
+}
+
+// Show value without quotes for strings.
+pub fn display(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        _ => value.to_string(),
+    }
+}
+
+// Parse configuration file. Errors have line and column where problem is.
+pub fn parse(content: &str) -> Result<Value, ConfigError> {
+    content.parse::<Value>().map_err(|e| {
+        let offset = e.span().map(|span| span.start).unwrap_or(0);
+        let before = &content[..offset.min(content.len())];
+        let line_start = before.rfind('\n').map(|index| index + 1).unwrap_or(0);
+        ConfigError::Parse {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message: e.message().to_string(),
+        }
+    })
+}
+
+// Find problems in configuration: unknown options and values of wrong type.
+pub fn validate(config: &Value) -> Vec<ConfigError> {
+    let reference = Value::try_from(Config::default()).unwrap();
+    let mut problems: Vec<ConfigError> = Vec::new();
+    let tables = match config.as_table() {
+        Some(tables) => tables,
+        None => return problems,
+    };
+
+    for (table_name, table) in tables {
+        let expected = match reference.get(table_name) {
+            Some(expected) => expected,
+            None => {
+                problems.push(ConfigError::UnknownKey(table_name.clone()));
+                continue;
+            }
+        };
+        let table = match table.as_table() {
+            Some(table) => table,
+            None => {
+                problems.push(ConfigError::BadValue(table_name.clone()));
+                continue;
+            }
+        };
+        for (option, value) in table {
+            let key = format!("{}.{}", table_name, option);
+            match expected.get(option) {
+                Some(expected) if expected.same_type(value) => {}
+                Some(_) => problems.push(ConfigError::BadValue(key)),
+                // Only `editors` and `aliases` tables can have any keys.
+                None if FREE_TABLES.contains(&table_name.as_str()) && value.is_str() => {}
+                None if FREE_TABLES.contains(&table_name.as_str()) => {
+                    problems.push(ConfigError::BadValue(key))
+                }
+                None => problems.push(ConfigError::UnknownKey(key)),
+            }
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference() -> Value {
+        Value::try_from(Config::default()).unwrap()
+    }
+
+    fn parse_error(content: &str) -> (usize, usize) {
+        match parse(content) {
+            Err(ConfigError::Parse { line, column, .. }) => (line, column),
+            other => panic!("expected parse error, got {:?}", other),
+        }
+    }
+
+    fn problems(content: &str) -> Vec<String> {
+        validate(&parse(content).unwrap())
+            .iter()
+            .map(|problem| match problem {
+                ConfigError::UnknownKey(key) => format!("unknown {}", key),
+                ConfigError::BadValue(key) => format!("bad {}", key),
+                ConfigError::Parse { .. } => String::from("parse"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_error_position() {
+        assert_eq!(parse_error("[options]\neditor = \"nvim\" x\n"), (2, 17));
+        assert_eq!(parse_error("= 1"), (1, 1));
+    }
+
+    #[test]
+    fn parse_error_column_counts_characters() {
+        // Characters before error take more than one byte.
+        assert_eq!(parse_error("[options]\neditor = \"ノート\" x\n"), (2, 16));
+        assert_eq!(
+            parse_error("# Заметки\n[options]\npager = 'ü' ?\n"),
+            (3, 13)
+        );
+    }
+
+    #[test]
+    fn valid_config_has_no_problems() {
+        let content = "[options]\neditor = \"nvim\"\nignore_case = true\n\n[hooks]\ntimeout = 5\n";
+        assert!(problems(content).is_empty());
+        assert!(problems("").is_empty());
+    }
+
+    #[test]
+    fn unknown_keys() {
+        assert_eq!(
+            problems("bogus = 1\n[options]\nbogus = \"x\"\n[unknown]\nkey = 1\n"),
+            ["unknown bogus", "unknown options.bogus", "unknown unknown"]
+        );
+    }
+
+    #[test]
+    fn values_of_wrong_type() {
+        assert_eq!(
+            problems("options = 1\n[hooks]\ntimeout = \"5\"\nabort_on_failure = 1\n"),
+            [
+                "bad hooks.abort_on_failure",
+                "bad hooks.timeout",
+                "bad options"
+            ]
+        );
+    }
+
+    #[test]
+    fn free_tables_take_any_string_keys() {
+        assert!(
+            problems("[editors]\nmd = \"code --wait\"\n[aliases]\ntodo = \"open TODO\"\n")
+                .is_empty()
+        );
+        assert_eq!(
+            problems("[editors]\nmd = 1\n[aliases]\ntodo = [\"open\"]\n"),
+            ["bad aliases.todo", "bad editors.md"]
+        );
+        assert_eq!(problems("editors = \"nvim\"\n"), ["bad editors"]);
+    }
+
+    #[test]
+    fn env_names() {
+        assert_eq!(env_name("editor"), "KAMIYA_EDITOR");
+        assert_eq!(env_name("options.name_template"), "KAMIYA_NAME_TEMPLATE");
+        assert_eq!(env_name("hooks.timeout"), "KAMIYA_HOOKS_TIMEOUT");
+        assert_eq!(env_name("hooks.post_take"), "KAMIYA_HOOKS_POST_TAKE");
+        assert_eq!(env_name("editors.md"), "KAMIYA_EDITORS_MD");
+    }
+
+    #[test]
+    fn set_key_parses_bools() {
+        let mut config = Value::Table(toml::Table::new());
+        for (raw, expected) in [
+            ("true", true),
+            ("yes", true),
+            ("on", true),
+            ("1", true),
+            ("false", false),
+            ("no", false),
+            ("off", false),
+            ("0", false),
+        ] {
+            set_key(&mut config, &reference(), "ignore_case", raw).unwrap();
+            assert_eq!(
+                get_key(&config, "options.ignore_case"),
+                Some(&Value::Boolean(expected))
+            );
+        }
+        assert!(matches!(
+            set_key(&mut config, &reference(), "ignore_case", "maybe"),
+            Err(ConfigError::BadValue(key)) if key == "options.ignore_case"
+        ));
+    }
+
+    #[test]
+    fn set_key_parses_integers() {
+        let mut config = Value::Table(toml::Table::new());
+        set_key(&mut config, &reference(), "hooks.timeout", "-5").unwrap();
+        assert_eq!(get_key(&config, "hooks.timeout"), Some(&Value::Integer(-5)));
+        for raw in ["", "5s", "1.5", "99999999999999999999"] {
+            assert!(matches!(
+                set_key(&mut config, &reference(), "hooks.timeout", raw),
+                Err(ConfigError::BadValue(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn set_key_in_free_tables() {
+        let mut config = Value::Table(toml::Table::new());
+        set_key(&mut config, &reference(), "editors.md", "code --wait").unwrap();
+        set_key(&mut config, &reference(), "aliases.todo", "open TODO").unwrap();
+        assert_eq!(
+            display(get_key(&config, "editors.md").unwrap()),
+            "code --wait"
+        );
+        assert_eq!(
+            display(get_key(&config, "aliases.todo").unwrap()),
+            "open TODO"
+        );
+        assert!(matches!(
+            set_key(&mut config, &reference(), "editors.md.x", "nvim"),
+            Err(ConfigError::UnknownKey(_))
+        ));
+    }
+
+    #[test]
+    fn set_key_rejects_unknown_keys() {
+        let mut config = Value::Table(toml::Table::new());
+        for key in ["bogus", "hooks.bogus", "unknown.key"] {
+            assert!(matches!(
+                set_key(&mut config, &reference(), key, "1"),
+                Err(ConfigError::UnknownKey(_))
+            ));
+        }
+        assert_eq!(config, Value::Table(toml::Table::new()));
+    }
 }
@@ -1,31 +1,103 @@
 use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use unicode_normalization::UnicodeNormalization;
 
 // Note structure.
 #[derive(Serialize, Deserialize, Default, Clone)]
 pub struct Note {
+    // Random ID which doesn't change when note is renamed. Empty ID is assigned when note is added.
+    #[serde(default)]
+    pub id: String,
     pub name: String,
     pub content: String,
     #[serde(default)]
     pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub secret: bool,
+    // Time of last change of the content, in seconds since Unix epoch.
+    #[serde(default)]
+    pub modified: u64,
 }
 
 // Kamiya database structure.
 #[derive(Serialize, Deserialize, Default)]
 pub struct Database {
     notes: Vec<Note>,
+    // Match names ignoring case and Unicode normal form. Not saved to the file.
+    #[serde(skip)]
+    ignore_case: bool,
 }
 
 // Errors which may occur.
 #[derive(Debug)]
 pub enum DatabaseError {
-    NoteNotFound,
-    BadTemplate,
+    NoteNotFound(String),
+    NoteExists(String),
+    BadTemplate(String),
+    // Name matches several notes. Contains the name and names of matched notes.
+    AmbiguousName(String, Vec<String>),
+}
+
+// What to do when note with the same name already exists.
+#[derive(Clone, Copy)]
+pub enum OnConflict {
+    Fail,
+    Overwrite,
+    // Add number to the name, like `Note (2)`.
+    Suffix,
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DatabaseError::NoteNotFound(name) => write!(f, "Note '{}' not found.", name),
+            DatabaseError::NoteExists(name) => write!(f, "Note '{}' already exists.", name),
+            DatabaseError::BadTemplate(template) => {
+                write!(f, "Template '{}' must contain `&i`.", template)
+            }
+            DatabaseError::AmbiguousName(name, matches) => write!(
+                f,
+                "Name '{}' matches several notes: '{}'.",
+                name,
+                matches.join("', '")
+            ),
+        }
+    }
+}
+
+impl Error for DatabaseError {}
+
+// Current time in seconds since Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// Name in form used for loose matching: NFC-normalized and lowercase.
+pub fn fold_name(name: &str) -> String {
+    name.nfc().collect::<String>().to_lowercase()
 }
 
 impl Database {
+    // Match names ignoring case and Unicode normal form.
+    pub fn set_ignore_case(&mut self, ignore_case: bool) {
+        self.ignore_case = ignore_case;
+    }
+
     // Check if note exists.
     pub fn note_exists(&self, name: &str) -> bool {
-        self.notes.iter().any(|item| item.name == *name.to_owned())
+        !matches!(
+            self.get_note_index(name),
+            Err(DatabaseError::NoteNotFound(_))
+        )
     }
 
     // Remove note from database.
@@ -44,22 +116,117 @@ impl Database {
         self.notes.clone()
     }
 
-    // Add new note to the database.
-    pub fn add_note(&mut self, new_note: Note) {
+    // Add new note to the database. Fails if note with the same name exists.
+    pub fn add_note(&mut self, new_note: Note) -> Result<(), DatabaseError> {
+        self.insert_note(new_note, OnConflict::Fail).map(|_| ())
+    }
+
+    // Add new note, resolving conflict with existing note. Returns name of added note.
+    pub fn insert_note(
+        &mut self,
+        mut new_note: Note,
+        on_conflict: OnConflict,
+    ) -> Result<String, DatabaseError> {
+        if new_note.modified == 0 {
+            new_note.modified = now();
+        }
+        if new_note.id.is_empty() || self.notes.iter().any(|note| note.id == new_note.id) {
+            new_note.id = self.new_id();
+        }
+        let existing = match self.get_note_index(&new_note.name) {
+            Ok(index) => Some(index),
+            Err(DatabaseError::NoteNotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+        match (existing, on_conflict) {
+            (None, _) => {}
+            (Some(_), OnConflict::Fail) => return Err(DatabaseError::NoteExists(new_note.name)),
+            (Some(index), OnConflict::Overwrite) => {
+                let name = new_note.name.clone();
+                // Note which is replaced keeps its ID.
+                new_note.id = self.notes[index].id.clone();
+                self.notes[index] = new_note;
+                return Ok(name);
+            }
+            (Some(_), OnConflict::Suffix) => new_note.name = self.unique_name(&new_note.name),
+        }
+        let name = new_note.name.clone();
         self.notes.push(new_note);
+        Ok(name)
     }
 
-    // Set new name for the note.
-    pub fn set_note_name(&mut self, note_name: &str, new_name: &str) -> Result<(), DatabaseError> {
-        match self.get_note_index(note_name) {
-            Ok(index) => {
-                self.notes[index].name = new_name.to_string();
-                Ok(())
+    // Generate random ID which is not used by other notes.
+    fn new_id(&self) -> String {
+        loop {
+            let mut bytes = [0u8; 8];
+            if getrandom::getrandom(&mut bytes).is_err() {
+                bytes = (SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or(0))
+                .to_le_bytes();
+            }
+            let id: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            if !self.notes.iter().any(|note| note.id == id) {
+                return id;
+            }
+        }
+    }
+
+    // Give IDs to notes which have no ID or share it with another note.
+    // Notes from older versions of Kamiya have no IDs.
+    pub fn assign_ids(&mut self) {
+        for index in 0..self.notes.len() {
+            let id = &self.notes[index].id;
+            if id.is_empty() || self.notes[..index].iter().any(|note| note.id == *id) {
+                self.notes[index].id = self.new_id();
             }
-            Err(e) => Err(e),
         }
     }
 
+    // Get name which is not taken yet, adding number to it if needed.
+    pub fn unique_name(&self, name: &str) -> String {
+        let mut candidate = name.to_string();
+        let mut counter = 2;
+        while self.note_exists(&candidate) {
+            candidate = format!("{} ({})", name, counter);
+            counter += 1;
+        }
+        candidate
+    }
+
+    // Set new name for the note. Fails if another note has this name.
+    pub fn set_note_name(&mut self, note_name: &str, new_name: &str) -> Result<(), DatabaseError> {
+        self.rename_note(note_name, new_name, OnConflict::Fail)
+            .map(|_| ())
+    }
+
+    // Set new name for the note, resolving conflict with another note. Returns new name.
+    pub fn rename_note(
+        &mut self,
+        note_name: &str,
+        new_name: &str,
+        on_conflict: OnConflict,
+    ) -> Result<String, DatabaseError> {
+        let index = self.get_note_index(note_name)?;
+        let mut new_name = new_name.to_string();
+        match self.get_note_index(&new_name) {
+            Ok(other) if other != index => match on_conflict {
+                OnConflict::Fail => return Err(DatabaseError::NoteExists(new_name)),
+                OnConflict::Overwrite => {
+                    self.notes.remove(other);
+                }
+                OnConflict::Suffix => new_name = self.unique_name(&new_name),
+            },
+            Ok(_) | Err(DatabaseError::NoteNotFound(_)) => {}
+            Err(e) if !matches!(on_conflict, OnConflict::Suffix) => return Err(e),
+            Err(_) => new_name = self.unique_name(&new_name),
+        }
+        let index = self.get_note_index(note_name)?;
+        self.notes[index].name = new_name.clone();
+        Ok(new_name)
+    }
+
     // Set content for note.
     pub fn set_note_content(
         &mut self,
@@ -69,6 +236,7 @@ impl Database {
         match self.get_note_index(note_name) {
             Ok(index) => {
                 self.notes[index].content = new_content.to_string();
+                self.notes[index].modified = now();
                 Ok(())
             }
             Err(e) => Err(e),
@@ -90,15 +258,40 @@ impl Database {
         }
     }
 
-    // Get note index by name.
+    // Mark note as secret. Content of secret notes is encrypted.
+    pub fn set_note_secret(&mut self, note_name: &str, secret: bool) -> Result<(), DatabaseError> {
+        match self.get_note_index(note_name) {
+            Ok(index) => {
+                self.notes[index].secret = secret;
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Get note index by name. Exact match always wins, even if names are matched loosely.
     pub fn get_note_index(&self, name: &str) -> Result<usize, DatabaseError> {
-        match self
-            .notes
-            .iter()
-            .position(|item| item.name == *name.to_owned())
-        {
-            Some(index) => Ok(index),
-            None => Err(DatabaseError::NoteNotFound),
+        if let Some(index) = self.notes.iter().position(|item| item.name == name) {
+            return Ok(index);
+        }
+        if !self.ignore_case {
+            return Err(DatabaseError::NoteNotFound(name.to_string()));
+        }
+
+        let folded = fold_name(name);
+        let matches: Vec<usize> = (0..self.notes.len())
+            .filter(|index| fold_name(&self.notes[*index].name) == folded)
+            .collect();
+        match matches.as_slice() {
+            [] => Err(DatabaseError::NoteNotFound(name.to_string())),
+            [index] => Ok(*index),
+            _ => Err(DatabaseError::AmbiguousName(
+                name.to_string(),
+                matches
+                    .iter()
+                    .map(|i| self.notes[*i].name.clone())
+                    .collect(),
+            )),
         }
     }
 
@@ -107,7 +300,7 @@ impl Database {
         match self.get_note_index(name) {
             Ok(index) => match self.notes.get(index) {
                 Some(i) => Ok(i.clone()),
-                None => Err(DatabaseError::NoteNotFound),
+                None => Err(DatabaseError::NoteNotFound(name.to_string())),
             },
             Err(e) => Err(e),
         }
@@ -116,10 +309,253 @@ impl Database {
     // Generate new name from template.
     pub fn generate_name(&self, template: &str) -> Result<String, DatabaseError> {
         if !template.contains("&i") {
-            return Err(DatabaseError::BadTemplate);
+            return Err(DatabaseError::BadTemplate(template.to_string()));
         }
         let note_number = self.notes.len() + 1;
         let new_name: String = template.replace("&i", &note_number.to_string());
         Ok(new_name)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(name: &str, content: &str) -> Note {
+        Note {
+            name: name.to_string(),
+            content: content.to_string(),
+            ..Note::default()
+        }
+    }
+
+    fn database(names: &[&str]) -> Database {
+        let mut database = Database::default();
+        for name in names {
+            database.add_note(note(name, "")).unwrap();
+        }
+        database
+    }
+
+    fn names(database: &Database) -> Vec<String> {
+        database
+            .get_notes()
+            .into_iter()
+            .map(|note| note.name)
+            .collect()
+    }
+
+    #[test]
+    fn insert_fails_on_existing_name() {
+        let mut database = database(&["a"]);
+        let result = database.insert_note(note("a", "new"), OnConflict::Fail);
+        assert!(matches!(result, Err(DatabaseError::NoteExists(name)) if name == "a"));
+        assert_eq!(database.get_note("a").unwrap().content, "");
+    }
+
+    #[test]
+    fn insert_overwrites_and_keeps_id() {
+        let mut database = database(&["a", "b"]);
+        let id = database.get_note("a").unwrap().id;
+        let name = database
+            .insert_note(note("a", "new"), OnConflict::Overwrite)
+            .unwrap();
+        assert_eq!(name, "a");
+        assert_eq!(names(&database), ["a", "b"]);
+        let replaced = database.get_note("a").unwrap();
+        assert_eq!(replaced.content, "new");
+        assert_eq!(replaced.id, id);
+    }
+
+    #[test]
+    fn insert_adds_suffix() {
+        let mut database = database(&["a"]);
+        let name = database
+            .insert_note(note("a", "new"), OnConflict::Suffix)
+            .unwrap();
+        assert_eq!(name, "a (2)");
+        assert_eq!(database.get_note("a (2)").unwrap().content, "new");
+    }
+
+    #[test]
+    fn suffix_skips_taken_names() {
+        let mut database = database(&["a", "a (2)", "a (3)"]);
+        let name = database
+            .insert_note(note("a", ""), OnConflict::Suffix)
+            .unwrap();
+        assert_eq!(name, "a (4)");
+        // Name with suffix gets its own suffix.
+        let name = database
+            .insert_note(note("a (2)", ""), OnConflict::Suffix)
+            .unwrap();
+        assert_eq!(name, "a (2) (2)");
+    }
+
+    #[test]
+    fn insert_without_conflict_keeps_name() {
+        let mut database = database(&["a"]);
+        for on_conflict in [OnConflict::Fail, OnConflict::Overwrite, OnConflict::Suffix] {
+            database.remove_note("b").ok();
+            assert_eq!(
+                database.insert_note(note("b", ""), on_conflict).unwrap(),
+                "b"
+            );
+        }
+        assert_eq!(names(&database), ["a", "b"]);
+    }
+
+    #[test]
+    fn inserted_notes_get_unique_ids() {
+        let mut database = database(&["a"]);
+        let mut copy = note("b", "");
+        copy.id = database.get_note("a").unwrap().id;
+        database.add_note(copy).unwrap();
+        let a = database.get_note("a").unwrap();
+        let b = database.get_note("b").unwrap();
+        assert!(!b.id.is_empty());
+        assert_ne!(a.id, b.id);
+    }
+
+    #[test]
+    fn rename_fails_on_existing_name() {
+        let mut database = database(&["a", "b"]);
+        let result = database.rename_note("a", "b", OnConflict::Fail);
+        assert!(matches!(result, Err(DatabaseError::NoteExists(name)) if name == "b"));
+        assert_eq!(names(&database), ["a", "b"]);
+    }
+
+    #[test]
+    fn rename_overwrites_other_note() {
+        let mut database = database(&["a", "b"]);
+        let id = database.get_note("a").unwrap().id;
+        let name = database
+            .rename_note("a", "b", OnConflict::Overwrite)
+            .unwrap();
+        assert_eq!(name, "b");
+        assert_eq!(names(&database), ["b"]);
+        assert_eq!(database.get_note("b").unwrap().id, id);
+    }
+
+    #[test]
+    fn rename_adds_suffix() {
+        let mut database = database(&["a", "b", "b (2)"]);
+        let name = database.rename_note("a", "b", OnConflict::Suffix).unwrap();
+        assert_eq!(name, "b (3)");
+        assert_eq!(names(&database), ["b (3)", "b", "b (2)"]);
+    }
+
+    #[test]
+    fn rename_to_the_same_name() {
+        let mut database = database(&["a"]);
+        for on_conflict in [OnConflict::Fail, OnConflict::Overwrite, OnConflict::Suffix] {
+            assert_eq!(database.rename_note("a", "a", on_conflict).unwrap(), "a");
+        }
+        assert_eq!(names(&database), ["a"]);
+    }
+
+    #[test]
+    fn rename_missing_note() {
+        let mut database = database(&["a"]);
+        let result = database.rename_note("b", "c", OnConflict::Suffix);
+        assert!(matches!(result, Err(DatabaseError::NoteNotFound(_))));
+    }
+
+    fn loose(names: &[&str]) -> Database {
+        let mut database = database(names);
+        database.set_ignore_case(true);
+        database
+    }
+
+    #[test]
+    fn fold_name_normalizes_and_lowercases() {
+        // `é` as one character and as `e` with combining accent.
+        assert_eq!(fold_name("Caf\u{e9}"), fold_name("CAFE\u{301}"));
+        assert_eq!(fold_name("TODO"), "todo");
+        assert_ne!(fold_name("todo"), fold_name("to do"));
+    }
+
+    #[test]
+    fn names_are_exact_by_default() {
+        let database = database(&["Todo"]);
+        assert!(matches!(
+            database.get_note_index("todo"),
+            Err(DatabaseError::NoteNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn ignore_case_finds_note() {
+        let database = loose(&["Todo", "Caf\u{e9}"]);
+        assert_eq!(database.get_note_index("TODO").unwrap(), 0);
+        assert_eq!(database.get_note_index("cafe\u{301}").unwrap(), 1);
+    }
+
+    #[test]
+    fn exact_match_wins() {
+        let database = loose(&["todo", "Todo", "TODO"]);
+        assert_eq!(database.get_note_index("Todo").unwrap(), 1);
+        assert_eq!(database.get_note_index("TODO").unwrap(), 2);
+    }
+
+    #[test]
+    fn ambiguous_name_is_reported() {
+        let database = loose(&["todo", "TODO", "other"]);
+        match database.get_note_index("Todo") {
+            Err(DatabaseError::AmbiguousName(name, matches)) => {
+                assert_eq!(name, "Todo");
+                assert_eq!(matches, ["todo", "TODO"]);
+            }
+            _ => panic!("name must be ambiguous"),
+        }
+        assert!(database.note_exists("Todo"));
+    }
+
+    #[test]
+    fn ignore_case_conflicts() {
+        let mut database = loose(&["Todo"]);
+        let result = database.insert_note(note("TODO", ""), OnConflict::Fail);
+        assert!(matches!(result, Err(DatabaseError::NoteExists(_))));
+        let name = database
+            .insert_note(note("TODO", ""), OnConflict::Suffix)
+            .unwrap();
+        assert_eq!(name, "TODO (2)");
+    }
+
+    #[test]
+    fn ambiguous_name_is_not_overwritten() {
+        let mut database = database(&["todo", "TODO"]);
+        database.set_ignore_case(true);
+        let result = database.insert_note(note("Todo", "new"), OnConflict::Overwrite);
+        assert!(matches!(result, Err(DatabaseError::AmbiguousName(_, _))));
+        assert_eq!(names(&database), ["todo", "TODO"]);
+    }
+
+    #[test]
+    fn rename_changes_case_of_name() {
+        let mut database = loose(&["todo", "other"]);
+        let renames = [
+            ("todo", "TODO", OnConflict::Fail),
+            ("TODO", "Todo", OnConflict::Overwrite),
+            ("Todo", "todo", OnConflict::Suffix),
+        ];
+        for (name, new_name, on_conflict) in renames {
+            assert_eq!(
+                database.rename_note(name, new_name, on_conflict).unwrap(),
+                new_name
+            );
+        }
+        assert_eq!(names(&database), ["todo", "other"]);
+    }
+
+    #[test]
+    fn rename_to_ambiguous_name() {
+        let mut database = database(&["a", "todo", "TODO"]);
+        database.set_ignore_case(true);
+        let result = database.rename_note("a", "Todo", OnConflict::Fail);
+        assert!(matches!(result, Err(DatabaseError::AmbiguousName(_, _))));
+        let name = database
+            .rename_note("a", "Todo", OnConflict::Suffix)
+            .unwrap();
+        assert_eq!(name, "Todo (2)");
+    }
+}
@@ -0,0 +1,175 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fmt};
+
+const FORMAT: &str = "kamiya-encrypted";
+const SALT_LENGTH: usize = 16;
+
+// Encrypted data with everything needed to decrypt it, except passphrase.
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    format: String,
+    version: u32,
+    kdf: String,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Debug)]
+pub enum CryptoError {
+    BadPassphrase,
+    BadFormat,
+    EncryptionFailed,
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CryptoError::BadPassphrase => write!(f, "Wrong passphrase."),
+            CryptoError::BadFormat => write!(f, "Encrypted data is corrupted."),
+            CryptoError::EncryptionFailed => write!(f, "Failed to encrypt data."),
+        }
+    }
+}
+
+impl Error for CryptoError {}
+
+fn derive_key(passphrase: &str, salt: &[u8], params: Params) -> Result<Key, CryptoError> {
+    let mut key = Key::default();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|_| CryptoError::BadFormat)?;
+    Ok(key)
+}
+
+// Check if data was encrypted with `encrypt`.
+pub fn is_encrypted(data: &str) -> bool {
+    match serde_json::from_str::<Envelope>(data) {
+        Ok(envelope) => envelope.format == FORMAT,
+        Err(_) => false,
+    }
+}
+
+// Encrypt data with key derived from passphrase. Result is a JSON string.
+pub fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<String, CryptoError> {
+    let params = Params::default();
+    let mut salt = [0u8; SALT_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+
+    let key =
+        derive_key(passphrase, &salt, params.clone()).map_err(|_| CryptoError::EncryptionFailed)?;
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(&key)
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| CryptoError::EncryptionFailed)?;
+
+    let envelope = Envelope {
+        format: String::from(FORMAT),
+        version: 1,
+        kdf: String::from("argon2id"),
+        m_cost: params.m_cost(),
+        t_cost: params.t_cost(),
+        p_cost: params.p_cost(),
+        salt: STANDARD.encode(salt),
+        nonce: STANDARD.encode(nonce),
+        ciphertext: STANDARD.encode(ciphertext),
+    };
+    serde_json::to_string(&envelope).map_err(|_| CryptoError::EncryptionFailed)
+}
+
+// Decrypt data made by `encrypt`. Fails if passphrase is wrong or data was modified.
+pub fn decrypt(data: &str, passphrase: &str) -> Result<Vec<u8>, CryptoError> {
+    let envelope: Envelope = serde_json::from_str(data).map_err(|_| CryptoError::BadFormat)?;
+    if envelope.format != FORMAT || envelope.kdf != "argon2id" {
+        return Err(CryptoError::BadFormat);
+    }
+
+    let salt = STANDARD
+        .decode(envelope.salt)
+        .map_err(|_| CryptoError::BadFormat)?;
+    let nonce = STANDARD
+        .decode(envelope.nonce)
+        .map_err(|_| CryptoError::BadFormat)?;
+    let ciphertext = STANDARD
+        .decode(envelope.ciphertext)
+        .map_err(|_| CryptoError::BadFormat)?;
+    if nonce.len() != 12 {
+        return Err(CryptoError::BadFormat);
+    }
+
+    let params = Params::new(envelope.m_cost, envelope.t_cost, envelope.p_cost, None)
+        .map_err(|_| CryptoError::BadFormat)?;
+    let key = derive_key(passphrase, &salt, params)?;
+    ChaCha20Poly1305::new(&key)
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| CryptoError::BadPassphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let data = "Secret note.\nСекрет.".as_bytes();
+        let encrypted = encrypt(data, "passphrase").unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert_eq!(decrypt(&encrypted, "passphrase").unwrap(), data);
+    }
+
+    #[test]
+    fn empty_data() {
+        let encrypted = encrypt(b"", "").unwrap();
+        assert_eq!(decrypt(&encrypted, "").unwrap(), b"");
+    }
+
+    #[test]
+    fn salt_and_nonce_are_random() {
+        let first = encrypt(b"data", "passphrase").unwrap();
+        let second = encrypt(b"data", "passphrase").unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn wrong_passphrase() {
+        let encrypted = encrypt(b"data", "passphrase").unwrap();
+        assert!(matches!(
+            decrypt(&encrypted, "Passphrase"),
+            Err(CryptoError::BadPassphrase)
+        ));
+    }
+
+    #[test]
+    fn modified_ciphertext() {
+        let encrypted = encrypt(b"data", "passphrase").unwrap();
+        let mut envelope: Envelope = serde_json::from_str(&encrypted).unwrap();
+        let mut ciphertext = STANDARD.decode(&envelope.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        envelope.ciphertext = STANDARD.encode(ciphertext);
+        let modified = serde_json::to_string(&envelope).unwrap();
+        // Modified data can't be told apart from wrong passphrase.
+        assert!(matches!(
+            decrypt(&modified, "passphrase"),
+            Err(CryptoError::BadPassphrase)
+        ));
+    }
+
+    #[test]
+    fn plain_text_is_not_encrypted() {
+        assert!(!is_encrypted("[[notes]]\nname = \"a\""));
+        assert!(!is_encrypted("{\"format\": \"other\"}"));
+        assert!(matches!(
+            decrypt("plain text", "passphrase"),
+            Err(CryptoError::BadFormat)
+        ));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod crypto;
 pub mod platform;
 pub mod proc;
 pub mod tempfile;
+pub mod walk;
@@ -1,5 +1,5 @@
 use home::home_dir;
-use std::env;
+use std::{env, error::Error, fmt, path::PathBuf};
 
 pub enum CurrentPlatform {
     Windows,
@@ -7,11 +7,23 @@ pub enum CurrentPlatform {
     Mac,
 }
 
+#[derive(Debug)]
 pub enum PlatformError {
     UnknownPlatform,
     UnsupportedSystem,
 }
 
+impl fmt::Display for PlatformError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlatformError::UnknownPlatform => write!(f, "Unknown platform '{}'.", env::consts::OS),
+            PlatformError::UnsupportedSystem => write!(f, "Home directory is not available."),
+        }
+    }
+}
+
+impl Error for PlatformError {}
+
 pub struct Platform;
 impl Platform {
     pub fn detect_platform() -> Result<CurrentPlatform, PlatformError> {
@@ -30,20 +42,8 @@ impl Platform {
         }
     }
 
-    pub fn get_temp_dir() -> Result<String, PlatformError> {
-        match Self::detect_platform() {
-            Ok(platform) => match platform {
-                CurrentPlatform::Mac => Result::Ok(String::from("/tmp/")),
-                CurrentPlatform::Linux => Result::Ok(String::from("/tmp/")),
-                CurrentPlatform::Windows => {
-                    let user_home_dir: String = match Self::get_user_home() {
-                        Ok(path) => path,
-                        Err(_) => panic!("Failed to get user home directory."),
-                    };
-                    Result::Ok(user_home_dir + "\\AppData\\Local\\Temp\\")
-                }
-            },
-            Err(e) => Result::Err(e),
-        }
+    // Temporary directory of the system. Respects `TMPDIR` on Unix and `TEMP` on Windows.
+    pub fn get_temp_dir() -> PathBuf {
+        env::temp_dir()
     }
 }
@@ -1,33 +1,229 @@
 use std::{
-    io::ErrorKind,
-    process::{Command, Stdio},
+    error::Error,
+    ffi::OsString,
+    fmt,
+    io::{self, ErrorKind, Write},
+    process::{Child, Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
 };
 
+#[derive(Debug)]
 pub enum ProcessError {
-    BadExitCode,
-    Interrupted,
-    ExecutableNotFound,
-    Unknown,
+    // Exit code, or `None` if process was killed by signal.
+    BadExitCode(Option<i32>),
+    BadCommand(String),
+    Interrupted(String),
+    ExecutableNotFound(String),
+    TimedOut(String),
+    Io(String, io::Error),
 }
 
-pub fn run_editor(editor: &str, path: &str) -> Result<(), ProcessError> {
-    let mut cmd = Command::new(editor);
-    cmd.arg(path);
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProcessError::BadExitCode(Some(code)) => {
+                write!(f, "Process exited with code {}.", code)
+            }
+            ProcessError::BadExitCode(None) => write!(f, "Process was killed by signal."),
+            ProcessError::BadCommand(command) => write!(f, "Command `{}` is not valid.", command),
+            ProcessError::Interrupted(program) => write!(f, "`{}` has been interrupted.", program),
+            ProcessError::ExecutableNotFound(program) => {
+                write!(f, "Executable `{}` not found.", program)
+            }
+            ProcessError::TimedOut(program) => write!(f, "`{}` has timed out.", program),
+            ProcessError::Io(program, e) => write!(f, "Failed to run `{}`: {}", program, e),
+        }
+    }
+}
+
+impl Error for ProcessError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProcessError::Io(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+fn spawn_error(program: &str, e: io::Error) -> ProcessError {
+    match e.kind() {
+        ErrorKind::Interrupted => ProcessError::Interrupted(program.to_string()),
+        ErrorKind::NotFound => ProcessError::ExecutableNotFound(program.to_string()),
+        _ => ProcessError::Io(program.to_string(), e),
+    }
+}
+
+fn check_status(status: ExitStatus) -> Result<(), ProcessError> {
+    match status.success() {
+        true => Ok(()),
+        false => Err(ProcessError::BadExitCode(status.code())),
+    }
+}
+
+// Run editor command to edit file. Command is parsed like in shell, `{file}` and `{line}`
+// placeholders are replaced with path to file and line number. If there is no `{file}`
+// placeholder, path is added as last argument.
+pub fn run_editor(editor: &str, path: &str, line: usize) -> Result<(), ProcessError> {
+    let mut args: Vec<String> = match shlex::split(editor) {
+        Some(args) if !args.is_empty() => args,
+        _ => return Err(ProcessError::BadCommand(editor.to_string())),
+    };
+    if !args.iter().any(|arg| arg.contains("{file}")) {
+        args.push(String::from("{file}"));
+    }
+    let args: Vec<String> = args
+        .iter()
+        .map(|arg| {
+            arg.replace("{file}", path)
+                .replace("{line}", &line.to_string())
+        })
+        .collect();
+
+    let mut cmd = Command::new(&args[0]);
+    cmd.args(&args[1..]);
     cmd.stdin(Stdio::inherit());
     cmd.stdout(Stdio::inherit());
     cmd.stderr(Stdio::inherit());
     match cmd.output() {
-        Ok(res) => {
-            if res.status.code().unwrap() != 0 {
-                return Err(ProcessError::BadExitCode);
+        Ok(res) => check_status(res.status),
+        Err(e) => Err(spawn_error(&args[0], e)),
+    }
+}
+
+pub fn run_pager(pager: &str, content: &str) -> Result<(), ProcessError> {
+    let parts: Vec<String> = match shlex::split(pager) {
+        Some(parts) if !parts.is_empty() => parts,
+        _ => return Err(ProcessError::BadCommand(pager.to_string())),
+    };
+
+    let mut cmd = Command::new(&parts[0]);
+    cmd.args(&parts[1..]);
+    cmd.stdin(Stdio::piped());
+    cmd.stdout(Stdio::inherit());
+    cmd.stderr(Stdio::inherit());
+    // Let `less` pass colors through and quit if content fits on one screen.
+    if std::env::var_os("LESS").is_none() {
+        cmd.env("LESS", "FRX");
+    }
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => return Err(spawn_error(&parts[0], e)),
+    };
+
+    // Pager may be closed before it reads everything, it's not an error.
+    // Pager is waited for in any case, so it doesn't stay as zombie.
+    let written = match child.stdin.take() {
+        Some(mut stdin) => match stdin.write_all(content.as_bytes()) {
+            Err(e) if e.kind() != ErrorKind::BrokenPipe => Err(e),
+            _ => Ok(()),
+        },
+        None => Ok(()),
+    };
+
+    let status = child.wait();
+    written.map_err(|e| ProcessError::Io(parts[0].clone(), e))?;
+    match status {
+        Ok(status) => check_status(status),
+        Err(e) => Err(ProcessError::Io(parts[0].clone(), e)),
+    }
+}
+
+// Run program connected to the terminal, with extra environment variables.
+// Returns exit code of the program, or `None` if it was killed by signal.
+pub fn run_program(
+    program: &str,
+    args: &[OsString],
+    env: &[(&str, String)],
+) -> Result<Option<i32>, ProcessError> {
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    cmd.envs(env.iter().map(|(key, value)| (key, value)));
+    cmd.stdin(Stdio::inherit());
+    cmd.stdout(Stdio::inherit());
+    cmd.stderr(Stdio::inherit());
+    match cmd.status() {
+        Ok(status) => Ok(status.code()),
+        Err(e) => Err(spawn_error(program, e)),
+    }
+}
+
+// Kill process with all its children. Process may exit right before it's killed, it's fine.
+fn kill_tree(child: &mut Child) {
+    #[cfg(unix)]
+    // SAFETY: `kill` has no memory effects. Process group of the child has the same ID as the child.
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    #[cfg(windows)]
+    let _ = Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &child.id().to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    let _ = child.kill();
+}
+
+// Run command with system shell and wait for it at most `timeout`, killing it after that.
+// Zero timeout means no limit.
+pub fn run_shell(
+    command: &str,
+    env: &[(&str, String)],
+    timeout: Duration,
+) -> Result<(), ProcessError> {
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    };
+    #[cfg(not(windows))]
+    let mut cmd = {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    };
+    cmd.envs(env.iter().map(|(key, value)| (key, value)));
+    cmd.stdin(Stdio::null());
+    // Output goes to stderr, so it doesn't mix with output of Kamiya.
+    cmd.stdout(io::stderr());
+    cmd.stderr(Stdio::inherit());
+    // Command gets its own process group, so everything it has started can be killed on timeout.
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => return Err(spawn_error(command, e)),
+    };
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return check_status(status),
+            Ok(None) if !timeout.is_zero() && started.elapsed() >= timeout => {
+                kill_tree(&mut child);
+                let _ = child.wait();
+                return Err(ProcessError::TimedOut(command.to_string()));
             }
+            Ok(None) => thread::sleep(Duration::from_millis(20)),
+            Err(e) => return Err(ProcessError::Io(command.to_string(), e)),
+        }
+    }
+}
 
-            Ok(())
+// Run git command in directory and return its output. Errors of git go to stderr.
+pub fn run_git(dir: &str, args: &[&str]) -> Result<String, ProcessError> {
+    let mut cmd = Command::new("git");
+    cmd.arg("-C").arg(dir).args(args);
+    cmd.stdin(Stdio::null());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::inherit());
+    match cmd.output() {
+        Ok(res) => {
+            check_status(res.status)?;
+            Ok(String::from_utf8_lossy(&res.stdout).to_string())
         }
-        Err(e) => match e.kind() {
-            ErrorKind::Interrupted => Err(ProcessError::Interrupted),
-            ErrorKind::NotFound => Err(ProcessError::ExecutableNotFound),
-            _ => Err(ProcessError::Unknown),
-        },
+        Err(e) => Err(spawn_error("git", e)),
     }
 }
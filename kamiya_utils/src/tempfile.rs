@@ -1,17 +1,52 @@
-use super::platform::{Platform, PlatformError};
-use std::{fs, io::Error};
+use super::platform::Platform;
+use std::{
+    fs::{self, OpenOptions},
+    io::{Error, ErrorKind},
+    path::PathBuf,
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+
+// Prefix of temporary files created by Kamiya.
+pub const PREFIX: &str = "kamiya-";
 
 pub struct TempFile {
     path: String,
+    keep: bool,
 }
 
 impl TempFile {
-    pub fn new(file_name: &str) -> Result<Self, PlatformError> {
-        match Platform::get_temp_dir() {
-            Ok(path) => Ok(Self {
-                path: path + file_name,
-            }),
-            Err(e) => Err(e),
+    // Create new empty file with unique random name, readable only by current user.
+    pub fn new(extension: Option<&str>) -> Result<Self, Error> {
+        let mut attempts = 0;
+        loop {
+            let mut bytes = [0u8; 8];
+            getrandom::getrandom(&mut bytes)?;
+            let mut file_name: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            file_name.insert_str(0, PREFIX);
+            if let Some(extension) = extension {
+                file_name.push('.');
+                file_name.push_str(extension);
+            }
+            let path = Platform::get_temp_dir().join(file_name);
+
+            let mut options = OpenOptions::new();
+            // Fails if file already exists, so nobody can prepare it for us.
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            options.mode(0o600);
+
+            match options.open(&path) {
+                Ok(_) => {
+                    return Ok(Self {
+                        path: path.display().to_string(),
+                        keep: false,
+                    })
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists && attempts < 10 => attempts += 1,
+                Err(e) => return Err(e),
+            }
         }
     }
 
@@ -19,6 +54,12 @@ impl TempFile {
         self.path.clone()
     }
 
+    // Keep file after `TempFile` is dropped. Returns path to the file.
+    pub fn keep(mut self) -> String {
+        self.keep = true;
+        self.path.clone()
+    }
+
     pub fn destroy(&self) -> Result<(), Error> {
         match fs::remove_file(&self.path) {
             Ok(_) => Ok(()),
@@ -26,3 +67,34 @@ impl TempFile {
         }
     }
 }
+
+// Find temporary files left by Kamiya, for example after crash or failed editor.
+pub fn find_leftovers() -> Result<Vec<PathBuf>, Error> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for entry in fs::read_dir(Platform::get_temp_dir())? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let random = match file_name.strip_prefix(PREFIX) {
+            Some(rest) => rest.split('.').next().unwrap_or_default(),
+            None => continue,
+        };
+        // Only names made by `TempFile::new`, so files of other programs are not touched.
+        if random.len() == 16
+            && random.chars().all(|c| c.is_ascii_hexdigit())
+            && entry.path().is_file()
+        {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        // Temporary file may contain private data, so it must not outlive Kamiya.
+        if !self.keep {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use std::{
+    fs,
+    io::Error,
+    path::{Path, PathBuf},
+};
+
+// Get all files inside directory and its subdirectories, skipping hidden ones.
+pub fn walk_dir(path: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect();
+    entries.sort();
+
+    for entry in entries {
+        let hidden = entry
+            .file_name()
+            .map(|name| name.to_string_lossy().starts_with('.'))
+            .unwrap_or(false);
+        if hidden {
+            continue;
+        }
+
+        if entry.is_dir() {
+            files.append(&mut walk_dir(&entry)?);
+        } else if entry.is_file() {
+            files.push(entry);
+        }
+    }
+    Ok(files)
+}